@@ -1,11 +1,14 @@
 use glam::DVec2;
-use rand::{distributions::Uniform, prelude::Distribution};
-use sdl2::rect::Rect;
+use rand::{distributions::Uniform, prelude::Distribution, rngs::StdRng};
 
 use crate::{
     ant_hill::AntHill,
+    food::FoodSources,
+    food_index::FoodIndex,
+    grid::Grid,
     marker::Marker,
-    tile::Tile,
+    tile::Terrain,
+    traits::TraitProfile,
     util::{map, map_pos_to_grid},
 };
 
@@ -14,9 +17,33 @@ const STATE_TARGET_FOOD: u8 = 1;
 const STATE_SEARCH_BACK: u8 = 2;
 const STATE_SEARCH_EXPLORE: u8 = 4;
 const STATE_TARGET_HOME: u8 = 3;
+const STATE_HUNGRY: u8 = 5;
+
+pub const NUM_STATES: usize = 6;
+
+// Ants this close to their target have arrived, in world units
+const ARRIVAL_TOLERANCE: f64 = 0.5;
+// Share of its energy below which an ant without food goes home to eat
+const HUNGER_THRESHOLD: f64 = 0.25;
+// Ticks an ant blocked by a wall wanders along it before heading for its target again
+const DETOUR_TICKS: u32 = 10;
+
+pub fn state_name(state: u8) -> &'static str {
+    match state {
+        STATE_WANDER => "wander",
+        STATE_TARGET_FOOD => "target food",
+        STATE_SEARCH_BACK => "search back",
+        STATE_TARGET_HOME => "target home",
+        STATE_SEARCH_EXPLORE => "search explore",
+        STATE_HUNGRY => "hungry",
+        _ => "unknown",
+    }
+}
 
 #[derive(Default)]
 pub struct Ant {
+    id: u64,
+    caste: usize,
     pos: DVec2,
 
     size: f64,
@@ -35,151 +62,256 @@ pub struct Ant {
 
     current_target_tile: (u32, u32),
     wander_target_dir: DVec2,
-}
+    detour_ticks: u32,
 
-impl Ant {
-    pub fn new(pos: DVec2) -> Self {
-        let mut random_gen = rand::thread_rng();
+    carrying: u32,
+    // Ticks since the ant last left the nest
+    trip_ticks: u32,
 
-        let random_marker_rate = Uniform::from(0..2);
-        let random_speed_size = Uniform::from(300000000..600000000);
-        let random_marker_perception_radius = Uniform::from(5..15);
-        let random_act_perception_radius = Uniform::from(25..65);
-        let random_wander_sway = Uniform::from(100000000..500000000);
-        let random_marker_strength = Uniform::from(300000000..500000000);
+    energy: f64,
+    max_energy: f64,
+}
 
+impl Ant {
+    pub fn new(
+        id: u64,
+        pos: DVec2,
+        caste: usize,
+        traits: &TraitProfile,
+        energy: f64,
+        random_gen: &mut StdRng,
+    ) -> Self {
         Self {
+            id: id,
+            caste: caste,
             pos: pos,
 
             state: 0,
 
-            act_perception_radius: random_act_perception_radius.sample(&mut random_gen) as f64,
-            perception_radius: random_marker_perception_radius.sample(&mut random_gen),
-            pheromone_radius: random_marker_perception_radius.sample(&mut random_gen),
+            act_perception_radius: traits.act_perception_radius.sample(random_gen),
+            perception_radius: traits.perception_radius.sample(random_gen) as u32,
+            pheromone_radius: traits.pheromone_radius.sample(random_gen) as u32,
 
-            size: random_speed_size.sample(&mut random_gen) as f64 * 0.00000001,
-            speed: random_speed_size.sample(&mut random_gen) as f64 * 0.00000001,
-            wander_direction_sway: random_wander_sway.sample(&mut random_gen) as f64 * 0.000000001,
+            size: traits.size.sample(random_gen),
+            speed: traits.speed.sample(random_gen),
+            wander_direction_sway: traits.wander_sway.sample(random_gen),
 
             ticks_since_marker: 0,
 
-            marker_drop_rate: random_marker_rate.sample(&mut random_gen),
-            marker_drop_strength: random_marker_strength.sample(&mut random_gen) as f64
-                * 0.000000001,
+            marker_drop_rate: traits.marker_drop_rate.sample(random_gen) as u32,
+            marker_drop_strength: traits.marker_strength.sample(random_gen),
 
             current_target_tile: (0, 0),
             wander_target_dir: DVec2::default(),
+            detour_ticks: 0,
+
+            carrying: 0,
+            trip_ticks: 0,
+
+            energy: energy,
+            max_energy: energy,
         }
     }
 
     pub fn update(
         &mut self,
-        win_dim: (u32, u32),
+        world_dim: (u32, u32),
         grid_size: (u32, u32),
-        world_tiles: &mut Vec<Vec<Tile>>,
-        ant_hill: &AntHill,
-        food_coords: &mut Vec<(u32, u32)>,
+        world_tiles: &mut Grid,
+        ant_hill: &mut AntHill,
+        food_sources: &mut FoodSources,
+        random_gen: &mut StdRng,
     ) {
+        self.trip_ticks += 1;
+
+        let previous_pos = self.pos;
+        let was_in_wall = world_tiles
+            .tile(self.map_pos_to_grid(grid_size, world_dim))
+            .terrain
+            == Terrain::Wall;
+
+        if self.detour_ticks > 0 {
+            self.detour_ticks -= 1;
+            self.wander(random_gen);
+            self.drop_marker(self.trail_marker(), world_tiles, grid_size, world_dim);
+        } else {
+            self.act(
+                world_dim,
+                grid_size,
+                world_tiles,
+                ant_hill,
+                food_sources,
+                random_gen,
+            );
+        }
+        self.wrap_screen(world_dim);
+
+        // Walls block movement. Wandering ants turn around, ants with a target walk along
+        // the wall for a while. Ants a wall was painted onto may leave it.
+        if !was_in_wall
+            && world_tiles
+                .tile(self.map_pos_to_grid(grid_size, world_dim))
+                .terrain
+                == Terrain::Wall
+        {
+            let step = self.pos - previous_pos;
+            self.pos = previous_pos;
+
+            if self.state == STATE_WANDER {
+                self.wander_target_dir = -self.wander_target_dir;
+            } else {
+                // Always the same way round, so repeated detours follow the wall to its end
+                self.wander_target_dir = DVec2::new(-step.y, step.x).normalize_or_zero();
+                self.detour_ticks = DETOUR_TICKS;
+            }
+        }
+    }
+
+    fn act(
+        &mut self,
+        world_dim: (u32, u32),
+        grid_size: (u32, u32),
+        world_tiles: &mut Grid,
+        ant_hill: &mut AntHill,
+        food_sources: &mut FoodSources,
+        random_gen: &mut StdRng,
+    ) {
+        // Ants carrying food are on their way home anyway
+        if self.carrying == 0
+            && self.state != STATE_HUNGRY
+            && self.energy < self.max_energy * HUNGER_THRESHOLD
+            && ant_hill.can_feed()
+        {
+            self.state = STATE_HUNGRY;
+        }
+
         // TODO implement logic
         match self.state {
             STATE_TARGET_FOOD => {
-                self.approach_food(grid_size, win_dim, world_tiles, food_coords);
-                self.drop_marker(1, world_tiles, grid_size, win_dim);
+                self.approach_food(grid_size, world_dim, world_tiles, food_sources);
+                self.drop_marker(1, world_tiles, grid_size, world_dim);
             }
             STATE_TARGET_HOME => {
-                self.approach_home(grid_size, win_dim, ant_hill);
-                self.drop_marker(2, world_tiles, grid_size, win_dim);
+                self.approach_home(ant_hill);
+                self.drop_marker(2, world_tiles, grid_size, world_dim);
+            }
+            STATE_HUNGRY => {
+                self.approach_home(ant_hill);
+                self.drop_marker(1, world_tiles, grid_size, world_dim);
             }
             STATE_SEARCH_EXPLORE => {
                 if !self.follow_marker(
                     2,
                     world_tiles,
                     grid_size,
-                    win_dim,
-                    food_coords,
-                    map_pos_to_grid(ant_hill.pos, grid_size, win_dim),
+                    world_dim,
+                    food_sources.index(),
+                    map_pos_to_grid(ant_hill.pos, grid_size, world_dim),
                 ) {
                     self.state = STATE_WANDER;
                 }
-                self.search_for_food(world_tiles, grid_size, win_dim);
-                self.drop_marker(1, world_tiles, grid_size, win_dim);
+                self.search_for_food(world_tiles, grid_size, world_dim);
+                self.drop_marker(1, world_tiles, grid_size, world_dim);
             }
             STATE_SEARCH_BACK => {
                 self.follow_marker(
                     1,
                     world_tiles,
                     grid_size,
-                    win_dim,
-                    food_coords,
-                    map_pos_to_grid(ant_hill.pos, grid_size, win_dim),
+                    world_dim,
+                    food_sources.index(),
+                    map_pos_to_grid(ant_hill.pos, grid_size, world_dim),
                 );
-                self.search_for_home(ant_hill.pos);
-                self.drop_marker(2, world_tiles, grid_size, win_dim);
+                self.search_for_home(ant_hill);
+                self.drop_marker(2, world_tiles, grid_size, world_dim);
             }
             _ => {
-                self.explore(world_tiles, grid_size, win_dim);
-                self.drop_marker(1, world_tiles, grid_size, win_dim);
+                self.explore(world_tiles, grid_size, world_dim, random_gen);
+                self.drop_marker(1, world_tiles, grid_size, world_dim);
             }
         }
-        self.wrap_screen(win_dim);
     }
 
-    pub fn move_to(&mut self, target: DVec2) {
+    // Ants on their way home lay the trail to food, all others the trail home
+    fn trail_marker(&self) -> u8 {
+        return match self.state {
+            STATE_TARGET_HOME | STATE_SEARCH_BACK => 2,
+            _ => 1,
+        };
+    }
+
+    /// Steps towards target without overshooting it, returns true once the ant is there
+    pub fn move_to(&mut self, target: DVec2) -> bool {
+        let distance = self.pos.distance(target);
+
+        if distance <= ARRIVAL_TOLERANCE {
+            return true;
+        }
+
+        if distance <= self.speed {
+            self.pos = target;
+            return true;
+        }
+
         let delta_x = self.pos.x - target.x;
         let delta_y = self.pos.y - target.y;
 
         let theta_radians = f64::atan2(delta_y, delta_x);
 
         self.pos += self.angle_to_vec(theta_radians) * self.speed;
+        return false;
     }
 
     pub fn explore(
         &mut self,
-        world_tiles: &mut Vec<Vec<Tile>>,
+        world_tiles: &mut Grid,
         grid_size: (u32, u32),
-        win_dim: (u32, u32),
+        world_dim: (u32, u32),
+        random_gen: &mut StdRng,
     ) {
-        self.wander();
-        self.search_for_food(world_tiles, grid_size, win_dim);
+        self.wander(random_gen);
+        self.search_for_food(world_tiles, grid_size, world_dim);
     }
 
-    fn wander(&mut self) {
-        let mut random_gen = rand::thread_rng();
+    fn wander(&mut self, random_gen: &mut StdRng) {
         let random_angle = Uniform::from(0..360);
 
-        let angle = random_angle.sample(&mut random_gen) as f64;
+        let angle = random_angle.sample(random_gen) as f64;
 
-        self.wander_target_dir = (self.wander_target_dir
+        // A sway of 0 or one that cancels the direction out keeps the previous direction
+        let direction = (self.wander_target_dir
             + self.angle_to_vec(angle) * self.wander_direction_sway)
-            .normalize();
+            .normalize_or_zero();
+        if direction != DVec2::ZERO {
+            self.wander_target_dir = direction;
+        } else if self.wander_target_dir == DVec2::ZERO {
+            self.wander_target_dir = self.angle_to_vec(angle);
+        }
 
         self.pos += self.wander_target_dir * self.speed;
     }
 
     fn search_for_food(
         &mut self,
-        world_tiles: &Vec<Vec<Tile>>,
+        world_tiles: &Grid,
         grid_size: (u32, u32),
-        win_dim: (u32, u32),
+        world_dim: (u32, u32),
     ) {
-        let (grid_x, grid_y) = self.map_pos_to_grid(grid_size, win_dim);
+        let (grid_x, grid_y) = self.map_pos_to_grid(grid_size, world_dim);
 
         let mut max_food_in_sight = 0;
         let mut found_food = false;
 
         for x in grid_x as i32 - self.perception_radius as i32
-            ..grid_x as i32 + self.perception_radius as i32
+            ..=grid_x as i32 + self.perception_radius as i32
         {
             for y in grid_y as i32 - self.perception_radius as i32
-                ..grid_y as i32 + self.perception_radius as i32
+                ..=grid_y as i32 + self.perception_radius as i32
             {
-                if y.is_positive()
-                    && x.is_positive()
-                    && y < grid_size.1 as i32
-                    && x < grid_size.0 as i32
-                {
-                    if world_tiles[x as usize][y as usize].food.concentration > max_food_in_sight {
-                        max_food_in_sight = world_tiles[x as usize][y as usize].food.concentration;
+                if y >= 0 && x >= 0 && y < grid_size.1 as i32 && x < grid_size.0 as i32 {
+                    let concentration = world_tiles.tile((x as u32, y as u32)).food.concentration;
+                    if concentration > max_food_in_sight {
+                        max_food_in_sight = concentration;
 
                         self.current_target_tile = (x as u32, y as u32);
                         found_food = true;
@@ -194,20 +326,30 @@ impl Ant {
     }
 
     // ? Well, at least it's O(n)
-    fn search_for_home(&mut self, ant_hill_pos: DVec2) {
-        if self.pos.distance(ant_hill_pos) <= self.act_perception_radius {
+    // The nest is spotted once its rim is within sight
+    fn search_for_home(&mut self, ant_hill: &AntHill) {
+        if self.pos.distance(ant_hill.pos) - ant_hill.radius() <= self.act_perception_radius {
             self.state = STATE_TARGET_HOME;
         }
     }
 
-    fn approach_home(&mut self, grid_size: (u32, u32), win_dim: (u32, u32), ant_hill: &AntHill) {
-        let (grid_x, grid_y) = self.map_pos_to_grid(grid_size, win_dim);
+    fn approach_home(&mut self, ant_hill: &mut AntHill) {
+        if !ant_hill.contains(self.pos) {
+            let target = ant_hill.home_target(self.pos);
+            self.move_to(target);
+        } else {
+            // Only trips that brought food back count towards the trip length
+            if self.carrying > 0 {
+                ant_hill.food_collected += self.carrying as u64;
+                ant_hill.food_stored += self.carrying as u64;
+                ant_hill.trips_completed += 1;
+                ant_hill.trip_ticks_total += self.trip_ticks as u64;
+            }
+            self.carrying = 0;
+            self.trip_ticks = 0;
 
-        let (home_x, home_y) = ant_hill.map_pos_to_grid(grid_size, win_dim);
+            self.energy += ant_hill.feed(self.max_energy - self.energy);
 
-        if grid_x != home_x && grid_y != home_y {
-            self.move_to(self.map_target_to_pos((home_x, home_y), grid_size, win_dim));
-        } else {
             self.state = STATE_SEARCH_EXPLORE;
         }
     }
@@ -215,35 +357,29 @@ impl Ant {
     fn drop_marker(
         &mut self,
         m_type: u8,
-        world_tiles: &mut Vec<Vec<Tile>>,
+        world_tiles: &mut Grid,
         grid_size: (u32, u32),
-        win_dim: (u32, u32),
+        world_dim: (u32, u32),
     ) {
         if !self.should_drop_marker() {
             self.ticks_since_marker += 1;
             return;
         }
 
-        let (grid_x, grid_y) = self.map_pos_to_grid(grid_size, win_dim);
+        let (grid_x, grid_y) = self.map_pos_to_grid(grid_size, world_dim);
+
+        if world_tiles.tile((grid_x, grid_y)).terrain == Terrain::Wall {
+            return;
+        }
 
         if m_type == 1 {
-            world_tiles[grid_x as usize][grid_y as usize]
-                .markers
-                .0
-                .m_type = 1;
-            world_tiles[grid_x as usize][grid_y as usize]
-                .markers
-                .0
-                .strength += self.marker_drop_strength;
+            let tile = world_tiles.tile_mut((grid_x, grid_y));
+            tile.markers.0.m_type = 1;
+            tile.markers.0.strength += self.marker_drop_strength;
         } else if m_type == 2 {
-            world_tiles[grid_x as usize][grid_y as usize]
-                .markers
-                .1
-                .m_type = 2;
-            world_tiles[grid_x as usize][grid_y as usize]
-                .markers
-                .1
-                .strength += self.marker_drop_strength;
+            let tile = world_tiles.tile_mut((grid_x, grid_y));
+            tile.markers.1.m_type = 2;
+            tile.markers.1.strength += self.marker_drop_strength;
         }
     }
 
@@ -258,17 +394,15 @@ impl Ant {
     fn approach_food(
         &mut self,
         grid_size: (u32, u32),
-        win_dim: (u32, u32),
-        grid: &mut Vec<Vec<Tile>>,
-        food_coords: &mut Vec<(u32, u32)>,
+        world_dim: (u32, u32),
+        grid: &mut Grid,
+        food_sources: &mut FoodSources,
     ) {
-        let (grid_x, grid_y) = self.map_pos_to_grid(grid_size, win_dim);
+        let target = self.map_target_to_pos(self.current_target_tile, grid_size, world_dim);
 
-        if grid_x != self.current_target_tile.0 && grid_y != self.current_target_tile.1 {
-            self.move_to(self.map_target_to_pos(self.current_target_tile, grid_size, win_dim));
-        } else {
+        if self.move_to(target) {
             self.state = STATE_SEARCH_BACK;
-            self.take_food(self.current_target_tile, food_coords, grid);
+            self.take_food(self.current_target_tile, food_sources, grid);
         }
     }
 
@@ -276,13 +410,13 @@ impl Ant {
     fn follow_marker(
         &mut self,
         m_type: u8,
-        world_tiles: &Vec<Vec<Tile>>,
+        world_tiles: &Grid,
         grid_size: (u32, u32),
-        win_dim: (u32, u32),
-        food_coords: &Vec<(u32, u32)>,
+        world_dim: (u32, u32),
+        food_index: &FoodIndex,
         home_coords: (u32, u32),
     ) -> bool {
-        let (grid_x, grid_y) = self.map_pos_to_grid(grid_size, win_dim);
+        let (grid_x, grid_y) = self.map_pos_to_grid(grid_size, world_dim);
 
         let mut closest_to_target_marker_in_sight = f64::MAX;
 
@@ -295,40 +429,43 @@ impl Ant {
         if m_type == 1 {
             targets.push(home_coords);
         } else if m_type == 2 {
-            targets.append(&mut food_coords.clone());
+            if let Some(food_coords) = food_index.nearest((grid_x, grid_y)) {
+                targets.push(food_coords);
+            }
         }
 
         for x in grid_x as i32 - self.pheromone_radius as i32
-            ..grid_x as i32 + self.pheromone_radius as i32
+            ..=grid_x as i32 + self.pheromone_radius as i32
         {
             for y in grid_y as i32 - self.pheromone_radius as i32
-                ..grid_y as i32 + self.pheromone_radius as i32
+                ..=grid_y as i32 + self.pheromone_radius as i32
             {
-                if y.is_positive()
-                    && x.is_positive()
+                // Only the ant's own tile is skipped
+                if y >= 0
+                    && x >= 0
                     && y < grid_size.1 as i32
                     && x < grid_size.0 as i32
-                    && x != grid_x as i32
-                    && y != grid_y as i32
+                    && (x, y) != (grid_x as i32, grid_y as i32)
                 {
                     let mut act_marker = Marker::new(0);
                     if m_type == 1 {
-                        act_marker = world_tiles[x as usize][y as usize].markers.0;
+                        act_marker = world_tiles.tile((x as u32, y as u32)).markers.0;
                     } else if m_type == 2 {
-                        act_marker = world_tiles[x as usize][y as usize].markers.1;
+                        act_marker = world_tiles.tile((x as u32, y as u32)).markers.1;
                     }
 
-                    if act_marker.m_type != 0 {
+                    // Markers that faded away keep their type but lead nowhere
+                    if act_marker.m_type != 0 && act_marker.strength > 0.0 {
                         found_marker = true;
 
                         let mut min_target_dist = f64::MAX;
                         for target in targets.iter() {
                             let target_dist = self
-                                .map_target_to_pos(*target, grid_size, win_dim)
+                                .map_target_to_pos(*target, grid_size, world_dim)
                                 .distance(self.map_target_to_pos(
                                     (x as u32, y as u32),
                                     grid_size,
-                                    win_dim,
+                                    world_dim,
                                 ));
                             if target_dist < min_target_dist {
                                 min_target_dist = target_dist;
@@ -346,26 +483,23 @@ impl Ant {
         }
 
         if found_marker {
-            self.move_to_grid_pos(marker_pos, grid_size, win_dim);
+            self.move_to_grid_pos(marker_pos, grid_size, world_dim);
         }
 
         return found_marker;
     }
 
-    fn move_to_grid_pos(&mut self, grid: (u32, u32), grid_size: (u32, u32), win_dim: (u32, u32)) {
-        let (grid_x, grid_y) = self.map_pos_to_grid(grid_size, win_dim);
-
-        if grid_x != grid.0 && grid_y != grid.1 {
-            self.move_to(self.map_target_to_pos(grid, grid_size, win_dim));
-        }
+    fn move_to_grid_pos(&mut self, grid: (u32, u32), grid_size: (u32, u32), world_dim: (u32, u32)) {
+        let target = self.map_target_to_pos(grid, grid_size, world_dim);
+        self.move_to(target);
     }
 
-    pub fn map_pos_to_grid(&self, grid_size: (u32, u32), window_size: (u32, u32)) -> (u32, u32) {
+    pub fn map_pos_to_grid(&self, grid_size: (u32, u32), world_size: (u32, u32)) -> (u32, u32) {
         return (
             (map(
                 self.pos.x,
                 0.0,
-                window_size.0 as f64,
+                world_size.0 as f64,
                 0.0,
                 grid_size.0 as f64,
             ) as u32)
@@ -373,7 +507,7 @@ impl Ant {
             (map(
                 self.pos.y,
                 0.0,
-                window_size.1 as f64,
+                world_size.1 as f64,
                 0.0,
                 grid_size.1 as f64,
             ) as u32)
@@ -381,38 +515,30 @@ impl Ant {
         );
     }
 
+    /// Center of the target tile in world units
     pub fn map_target_to_pos(
         &self,
         target: (u32, u32),
         grid_size: (u32, u32),
-        window_size: (u32, u32),
+        world_size: (u32, u32),
     ) -> DVec2 {
         return DVec2::new(
             (map(
-                target.0 as f64,
+                target.0 as f64 + 0.5,
                 0.0,
                 grid_size.0 as f64,
                 0.0,
-                window_size.0 as f64,
+                world_size.0 as f64,
             ) as f64)
-                .clamp(0.0, window_size.0 as f64),
+                .clamp(0.0, world_size.0 as f64),
             (map(
-                target.1 as f64,
+                target.1 as f64 + 0.5,
                 0.0,
                 grid_size.1 as f64,
                 0.0,
-                window_size.1 as f64,
+                world_size.1 as f64,
             ) as f64)
-                .clamp(0.0, window_size.1 as f64),
-        );
-    }
-
-    pub fn get_render_target(&self) -> Rect {
-        return Rect::new(
-            self.pos.x as i32 - self.size as i32 / 2,
-            self.pos.y as i32 - self.size as i32 / 2,
-            self.size as u32,
-            self.size as u32,
+                .clamp(0.0, world_size.1 as f64),
         );
     }
 
@@ -420,43 +546,373 @@ impl Ant {
         return -DVec2::new(radians.cos(), radians.sin()).normalize_or_zero();
     }
 
-    fn wrap_screen(&mut self, win_dim: (u32, u32)) {
+    fn wrap_screen(&mut self, world_dim: (u32, u32)) {
         if self.pos.x < 0.0 {
-            self.pos.x = win_dim.0 as f64;
-        } else if self.pos.x > win_dim.0 as f64 {
+            self.pos.x = world_dim.0 as f64;
+        } else if self.pos.x > world_dim.0 as f64 {
             self.pos.x = 0.0;
         }
 
         if self.pos.y < 0.0 {
-            self.pos.y = win_dim.1 as f64;
-        } else if self.pos.y > win_dim.1 as f64 {
+            self.pos.y = world_dim.1 as f64;
+        } else if self.pos.y > world_dim.1 as f64 {
             self.pos.y = 0.0;
         }
     }
 
-    pub fn set_pos(&mut self, pos: DVec2) {
-        self.pos = pos;
+    pub fn drain_energy(&mut self, amount: f64) {
+        self.energy -= amount;
     }
 
-    fn take_food(
-        &mut self,
-        grid_pos: (u32, u32),
-        food_coords: &mut Vec<(u32, u32)>,
-        grid: &mut Vec<Vec<Tile>>,
-    ) {
-        if !grid[grid_pos.0 as usize][grid_pos.1 as usize].sub_food() {
-            let rm_index;
-            let index_search = food_coords.binary_search(&grid_pos);
+    pub fn is_alive(&self) -> bool {
+        return self.energy > 0.0;
+    }
 
-            match index_search {
-                Ok(_) => {
-                    rm_index = index_search.unwrap();
-                }
-                Err(_e) => {
-                    return;
+    pub fn energy(&self) -> f64 {
+        return self.energy;
+    }
+
+    pub fn id(&self) -> u64 {
+        return self.id;
+    }
+
+    pub fn pos(&self) -> DVec2 {
+        return self.pos;
+    }
+
+    pub fn caste(&self) -> usize {
+        return self.caste;
+    }
+
+    pub fn size(&self) -> f64 {
+        return self.size;
+    }
+
+    pub fn speed(&self) -> f64 {
+        return self.speed;
+    }
+
+    pub fn perception_radius(&self) -> u32 {
+        return self.perception_radius;
+    }
+
+    pub fn pheromone_radius(&self) -> u32 {
+        return self.pheromone_radius;
+    }
+
+    pub fn current_target_tile(&self) -> (u32, u32) {
+        return self.current_target_tile;
+    }
+
+    pub fn state(&self) -> u8 {
+        return self.state;
+    }
+
+    pub fn carrying(&self) -> u32 {
+        return self.carrying;
+    }
+
+    /// Replaces one of the TRAIT_NAMES, e.g. after its distribution changed in the config
+    pub fn set_trait(&mut self, name: &str, value: f64) {
+        match name {
+            "speed" => self.speed = value,
+            "size" => self.size = value,
+            "perception_radius" => self.perception_radius = value as u32,
+            "pheromone_radius" => self.pheromone_radius = value as u32,
+            "act_perception_radius" => self.act_perception_radius = value,
+            "wander_sway" => self.wander_direction_sway = value,
+            "marker_strength" => self.marker_drop_strength = value,
+            "marker_drop_rate" => self.marker_drop_rate = value as u32,
+            _ => unreachable!("unknown trait '{}'", name),
+        }
+    }
+
+    fn take_food(&mut self, grid_pos: (u32, u32), food_sources: &mut FoodSources, grid: &mut Grid) {
+        if food_sources.harvest(grid, grid_pos) {
+            self.carrying += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ant_hill::NestConfig, traits::TraitDist};
+    use rand::SeedableRng;
+    use sdl2::pixels::Color;
+
+    const GRID_SIZE: (u32, u32) = (40, 40);
+    const WORLD_DIM: (u32, u32) = (400, 400);
+
+    fn fixed_traits() -> TraitProfile {
+        return TraitProfile {
+            speed: TraitDist::Fixed(3.0),
+            size: TraitDist::Fixed(3.0),
+            perception_radius: TraitDist::Fixed(5.0),
+            pheromone_radius: TraitDist::Fixed(5.0),
+            act_perception_radius: TraitDist::Fixed(30.0),
+            wander_sway: TraitDist::Fixed(0.3),
+            marker_strength: TraitDist::Fixed(0.4),
+            marker_drop_rate: TraitDist::Fixed(1.0),
+        };
+    }
+
+    fn ant_at(pos: DVec2, random_gen: &mut StdRng) -> Ant {
+        return Ant::new(0, pos, 0, &fixed_traits(), 1000.0, random_gen);
+    }
+
+    fn hill_at(pos: DVec2) -> AntHill {
+        let nest = NestConfig {
+            pos: None,
+            radius: 12.5,
+            color: Color::RGB(0, 0, 0),
+            entrances: 0,
+        };
+        return AntHill::new(pos, &nest, 0.0);
+    }
+
+    #[test]
+    fn reaches_the_exact_food_tile_in_its_row() {
+        let mut random_gen = StdRng::seed_from_u64(1);
+        let mut grid = Grid::new(GRID_SIZE);
+        let mut food_sources = FoodSources::new();
+        let mut ant_hill = hill_at(DVec2::new(200.0, 350.0));
+
+        food_sources.add(&mut grid, (25, 5), 0, vec![(25, 5)], 10, 0.0);
+
+        // Same row as the food, which used to count as arrived straight away
+        let mut ant = ant_at(DVec2::new(55.0, 55.0), &mut random_gen);
+        ant.state = STATE_TARGET_FOOD;
+        ant.current_target_tile = (25, 5);
+
+        for _ in 0..200 {
+            ant.update(
+                WORLD_DIM,
+                GRID_SIZE,
+                &mut grid,
+                &mut ant_hill,
+                &mut food_sources,
+                &mut random_gen,
+            );
+
+            if ant.carrying() > 0 {
+                break;
+            }
+            assert_eq!(grid.tile((25, 5)).food.concentration, 10);
+        }
+
+        assert_eq!(ant.carrying(), 1);
+        assert_eq!(ant.map_pos_to_grid(GRID_SIZE, WORLD_DIM), (25, 5));
+        assert_eq!(grid.tile((25, 5)).food.concentration, 9);
+    }
+
+    #[test]
+    fn reaches_the_nest_and_delivers() {
+        let mut random_gen = StdRng::seed_from_u64(2);
+        let mut grid = Grid::new(GRID_SIZE);
+        let mut food_sources = FoodSources::new();
+        let mut ant_hill = hill_at(DVec2::new(200.0, 200.0));
+
+        let mut ant = ant_at(DVec2::new(50.0, 200.0), &mut random_gen);
+        ant.state = STATE_TARGET_HOME;
+        ant.carrying = 3;
+
+        for _ in 0..200 {
+            ant.update(
+                WORLD_DIM,
+                GRID_SIZE,
+                &mut grid,
+                &mut ant_hill,
+                &mut food_sources,
+                &mut random_gen,
+            );
+
+            if ant_hill.food_collected > 0 {
+                break;
+            }
+        }
+
+        assert_eq!(ant_hill.food_collected, 3);
+        assert_eq!(ant_hill.trips_completed, 1);
+        assert_eq!(ant.carrying(), 0);
+        assert!(ant.pos().distance(ant_hill.pos) <= ant_hill.radius());
+    }
+
+    #[test]
+    fn hungry_ants_go_home_to_eat() {
+        let mut random_gen = StdRng::seed_from_u64(7);
+        let mut grid = Grid::new(GRID_SIZE);
+        let mut food_sources = FoodSources::new();
+        let nest = NestConfig {
+            pos: None,
+            radius: 12.5,
+            color: Color::RGB(0, 0, 0),
+            entrances: 0,
+        };
+        let mut ant_hill = AntHill::new(DVec2::new(200.0, 200.0), &nest, 100.0);
+
+        // Nothing stored, nothing to go home for
+        let mut ant = ant_at(DVec2::new(50.0, 300.0), &mut random_gen);
+        ant.energy = 200.0;
+        ant.update(
+            WORLD_DIM,
+            GRID_SIZE,
+            &mut grid,
+            &mut ant_hill,
+            &mut food_sources,
+            &mut random_gen,
+        );
+        assert_eq!(ant.state(), STATE_WANDER);
+
+        ant_hill.food_stored = 20;
+        for _ in 0..200 {
+            ant.update(
+                WORLD_DIM,
+                GRID_SIZE,
+                &mut grid,
+                &mut ant_hill,
+                &mut food_sources,
+                &mut random_gen,
+            );
+            if ant.state() != STATE_HUNGRY {
+                break;
+            }
+        }
+
+        // Eaten up to full energy, without counting as a delivery
+        assert_eq!(ant.energy(), 1000.0);
+        assert_eq!(ant_hill.food_stored, 12);
+        assert_eq!(ant_hill.trips_completed, 0);
+        assert_eq!(ant.state(), STATE_SEARCH_EXPLORE);
+        assert!(ant.pos().distance(ant_hill.pos) <= ant_hill.radius());
+    }
+
+    #[test]
+    fn walks_around_a_wall_between_it_and_the_nest() {
+        let mut random_gen = StdRng::seed_from_u64(5);
+        let mut grid = Grid::new(GRID_SIZE);
+        let mut food_sources = FoodSources::new();
+        let mut ant_hill = hill_at(DVec2::new(200.0, 200.0));
+
+        // Wall across the straight line from the ant to the nest
+        for y in 12..=28 {
+            grid.tile_mut((10, y)).terrain = Terrain::Wall;
+        }
+
+        let mut ant = ant_at(DVec2::new(50.0, 200.0), &mut random_gen);
+        ant.state = STATE_TARGET_HOME;
+        ant.carrying = 3;
+
+        for _ in 0..1000 {
+            ant.update(
+                WORLD_DIM,
+                GRID_SIZE,
+                &mut grid,
+                &mut ant_hill,
+                &mut food_sources,
+                &mut random_gen,
+            );
+            assert_ne!(
+                grid.tile(ant.map_pos_to_grid(GRID_SIZE, WORLD_DIM)).terrain,
+                Terrain::Wall
+            );
+
+            if ant_hill.food_collected > 0 {
+                break;
+            }
+        }
+
+        assert_eq!(ant_hill.food_collected, 3);
+    }
+
+    #[test]
+    fn wanders_straight_without_sway() {
+        let mut random_gen = StdRng::seed_from_u64(6);
+        let mut traits = fixed_traits();
+        traits.wander_sway = TraitDist::Fixed(0.0);
+        let mut ant = Ant::new(
+            0,
+            DVec2::new(200.0, 200.0),
+            0,
+            &traits,
+            1000.0,
+            &mut random_gen,
+        );
+
+        ant.wander(&mut random_gen);
+        let direction = ant.wander_target_dir;
+        for _ in 0..20 {
+            ant.wander(&mut random_gen);
+        }
+        assert!(ant.pos().is_finite());
+        assert_eq!(ant.wander_target_dir, direction);
+        assert!((ant.pos().distance(DVec2::new(200.0, 200.0)) - 63.0).abs() < 1e-9);
+
+        // A sway that exactly cancels the direction keeps it
+        let mut ant = ant_at(DVec2::new(200.0, 200.0), &mut random_gen);
+        ant.wander_direction_sway = 1.0;
+        let angle = Uniform::from(0..360).sample(&mut random_gen.clone()) as f64;
+        ant.wander_target_dir = -ant.angle_to_vec(angle);
+        let direction = ant.wander_target_dir;
+
+        ant.wander(&mut random_gen);
+        assert_eq!(ant.wander_target_dir, direction);
+        assert_eq!(ant.pos(), DVec2::new(200.0, 200.0) + direction * 3.0);
+    }
+
+    #[test]
+    fn follow_marker_sees_markers_in_its_row_and_on_the_edges() {
+        let mut random_gen = StdRng::seed_from_u64(3);
+        let mut grid = Grid::new(GRID_SIZE);
+        let food_index = FoodIndex::default();
+
+        // Same row, at the far edge of the pheromone radius
+        grid.tile_mut((15, 10)).markers.1 = Marker::new(2);
+
+        let mut ant = ant_at(DVec2::new(105.0, 105.0), &mut random_gen);
+        assert!(ant.follow_marker(2, &grid, GRID_SIZE, WORLD_DIM, &food_index, (0, 0)));
+
+        // Grid column 0 used to be skipped
+        let mut grid = Grid::new(GRID_SIZE);
+        grid.tile_mut((0, 2)).markers.1 = Marker::new(2);
+
+        let mut ant = ant_at(DVec2::new(25.0, 25.0), &mut random_gen);
+        assert!(ant.follow_marker(2, &grid, GRID_SIZE, WORLD_DIM, &food_index, (0, 0)));
+    }
+
+    #[test]
+    fn faded_trails_are_ignored() {
+        let mut random_gen = StdRng::seed_from_u64(8);
+        let mut grid = Grid::new(GRID_SIZE);
+        let food_index = FoodIndex::default();
+
+        for x in 11..=15 {
+            grid.tile_mut((x, 10)).markers.1 = Marker::new(2);
+            grid.tile_mut((x, 10)).markers.1.fade(1000.0);
+        }
+
+        let mut ant = ant_at(DVec2::new(105.0, 105.0), &mut random_gen);
+        assert!(!ant.follow_marker(2, &grid, GRID_SIZE, WORLD_DIM, &food_index, (0, 0)));
+        assert_eq!(ant.pos(), DVec2::new(105.0, 105.0));
+
+        // One tile with strength left is followed again
+        grid.tile_mut((15, 10)).markers.1.strength = 1.0;
+        assert!(ant.follow_marker(2, &grid, GRID_SIZE, WORLD_DIM, &food_index, (0, 0)));
+    }
+
+    #[test]
+    fn grid_positions_round_trip_through_world_positions() {
+        let mut random_gen = StdRng::seed_from_u64(4);
+        let mut ant = ant_at(DVec2::default(), &mut random_gen);
+
+        // Tiles that aren't whole numbers of world units too
+        for (grid_size, world_dim) in [(GRID_SIZE, WORLD_DIM), ((30, 17), (250, 170))].iter() {
+            for x in 0..grid_size.0 {
+                for y in 0..grid_size.1 {
+                    ant.pos = ant.map_target_to_pos((x, y), *grid_size, *world_dim);
+                    assert_eq!(ant.map_pos_to_grid(*grid_size, *world_dim), (x, y));
                 }
             }
-            food_coords.remove(rm_index);
         }
     }
 }
@@ -1,50 +1,172 @@
 use glam::DVec2;
-use sdl2::{pixels::Color, rect::Rect, render::Canvas, video::Window};
+use rand::{rngs::StdRng, Rng};
+use sdl2::pixels::Color;
+use std::f64::consts::PI;
 
-use crate::util::*;
+use crate::{config::Config, map_file::MapFile, util::*};
+
+// Share of the nest radius within which an entrance counts as reached
+pub const ENTRANCE_SIZE: f64 = 0.5;
+
+/// Where and how the nest is placed, see `NestConfig::from_config`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NestConfig {
+    /// Grid position, None puts the nest in the middle of the grid
+    pub pos: Option<(u32, u32)>,
+    pub radius: f64,
+    pub color: Color,
+    /// 0 lets ants in and out anywhere on the rim
+    pub entrances: u32,
+}
+
+impl NestConfig {
+    /// A nest line in the map file overrides the position and radius from the config
+    pub fn from_config(config: &Config) -> Self {
+        let pos_words = config.get_words("nest_pos");
+        let mut nest = Self {
+            pos: if pos_words[0] == "center" {
+                None
+            } else {
+                Some((
+                    config.get_parameter("nest_pos").vals[0] as u32,
+                    config.get_parameter("nest_pos").vals[1] as u32,
+                ))
+            },
+            radius: config.get_parameter("nest_radius").vals[0],
+            color: Color::RGB(
+                config.get_parameter("nest_color").vals[0] as u8,
+                config.get_parameter("nest_color").vals[1] as u8,
+                config.get_parameter("nest_color").vals[2] as u8,
+            ),
+            entrances: config.get_parameter("nest_entrances").vals[0] as u32,
+        };
+
+        if let Some(entry) = MapFile::from_config(config).and_then(|map| map.nest) {
+            nest.pos = Some(entry.pos);
+            if let Some(radius) = entry.radius {
+                nest.radius = radius;
+            }
+        }
+
+        return nest;
+    }
+}
 
 pub struct AntHill {
     pub pos: DVec2,
-    size: f64,
+    radius: f64,
+    entrances: u32,
     pub food_collected: u64,
+    /// Deliveries and the ticks they took, counted from leaving the nest
+    pub trips_completed: u64,
+    pub trip_ticks_total: u64,
+    /// Delivered food not yet eaten or spent on births
+    pub food_stored: u64,
+    energy_per_food: f64,
     color: Color,
 }
 
 impl AntHill {
-    pub fn new(pos: DVec2, size: f64) -> Self {
+    pub fn new(pos: DVec2, nest: &NestConfig, energy_per_food: f64) -> Self {
         return Self {
             pos: pos,
-            size: size,
+            radius: nest.radius,
+            entrances: nest.entrances,
             food_collected: 0,
-            color: Color::RGBA(200, 80, 5, 255),
+            trips_completed: 0,
+            trip_ticks_total: 0,
+            food_stored: 0,
+            energy_per_food: energy_per_food,
+            color: nest.color,
         };
     }
 
-    pub fn render(&self, canvas: &mut Canvas<Window>) {
-        let previous_color = canvas.draw_color();
-        canvas.set_draw_color(self.color);
+    pub fn radius(&self) -> f64 {
+        return self.radius;
+    }
+
+    pub fn set_energy_per_food(&mut self, energy_per_food: f64) {
+        self.energy_per_food = energy_per_food;
+    }
+
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    /// Entrances are spread evenly around the rim, starting on the right
+    pub fn entrance_positions(&self) -> Vec<DVec2> {
+        return (0..self.entrances)
+            .map(|index| {
+                let angle = 2.0 * PI * index as f64 / self.entrances as f64;
+                self.pos + DVec2::new(angle.cos(), angle.sin()) * self.radius
+            })
+            .collect();
+    }
+
+    /// Random spot on the rim, or at one of the entrances
+    pub fn spawn_pos(&self, random_gen: &mut StdRng) -> DVec2 {
+        let entrances = self.entrance_positions();
+        if !entrances.is_empty() {
+            return entrances[random_gen.gen_range(0..entrances.len())];
+        }
+
+        let angle = random_gen.gen_range(0.0..2.0 * PI);
+        return self.pos + DVec2::new(angle.cos(), angle.sin()) * self.radius;
+    }
+
+    /// Point an ant at pos should walk to, the closest entrance if there are any
+    pub fn home_target(&self, pos: DVec2) -> DVec2 {
+        let mut target = self.pos;
+        let mut closest = f64::MAX;
 
-        match canvas.fill_rect(Rect::new(
-            self.pos.x as i32 - self.size as i32 / 2,
-            self.pos.y as i32 - self.size as i32 / 2,
-            self.size as u32,
-            self.size as u32,
-        )) {
-            Ok(_) => {}
-            Err(e) => {
-                log::error!("render error: {}", e)
+        for entrance in self.entrance_positions() {
+            let distance = entrance.distance(pos);
+            if distance < closest {
+                closest = distance;
+                target = entrance;
             }
         }
 
-        canvas.set_draw_color(previous_color);
+        return target;
     }
 
-    pub fn map_pos_to_grid(&self, grid_size: (u32, u32), window_size: (u32, u32)) -> (u32, u32) {
+    /// Whether an ant at pos has made it into the nest
+    pub fn contains(&self, pos: DVec2) -> bool {
+        if self.entrances == 0 {
+            return pos.distance(self.pos) <= self.radius;
+        }
+
+        return pos.distance(self.home_target(pos)) <= self.radius * ENTRANCE_SIZE;
+    }
+
+    /// Whether feed would give energy at all
+    pub fn can_feed(&self) -> bool {
+        return self.food_stored > 0 && self.energy_per_food > 0.0;
+    }
+
+    /// Eats whole units of stored food that fit into missing_energy, returns the energy gained
+    pub fn feed(&mut self, missing_energy: f64) -> f64 {
+        if self.energy_per_food <= 0.0 || missing_energy <= 0.0 {
+            return 0.0;
+        }
+
+        let wanted = (missing_energy / self.energy_per_food).floor() as u64;
+        let eaten = wanted.min(self.food_stored);
+        self.food_stored -= eaten;
+
+        return eaten as f64 * self.energy_per_food;
+    }
+
+    pub fn color(&self) -> Color {
+        return self.color;
+    }
+
+    pub fn map_pos_to_grid(&self, grid_size: (u32, u32), world_size: (u32, u32)) -> (u32, u32) {
         return (
             (map(
                 self.pos.x,
                 0.0,
-                window_size.0 as f64,
+                world_size.0 as f64,
                 0.0,
                 grid_size.0 as f64,
             ) as u32)
@@ -52,7 +174,7 @@ impl AntHill {
             (map(
                 self.pos.y,
                 0.0,
-                window_size.1 as f64,
+                world_size.1 as f64,
                 0.0,
                 grid_size.1 as f64,
             ) as u32)
@@ -60,3 +182,34 @@ impl AntHill {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    #[test]
+    fn nest_comes_from_the_config_or_the_map_file() {
+        let mut config = fixture::config();
+
+        let nest = NestConfig::from_config(&config);
+        assert_eq!(nest.pos, Some((20, 20)));
+        assert_eq!(nest.radius, 12.5);
+        assert_eq!(nest.color, Color::RGB(200, 80, 5));
+        assert_eq!(nest.entrances, 0);
+
+        config.set_parameter("nest_pos", "center");
+        assert_eq!(NestConfig::from_config(&config).pos, None);
+
+        // An absolute path replaces the data folder
+        let path = std::env::temp_dir().join(format!("ants_nest_{}.map", std::process::id()));
+        std::fs::write(&path, "food 1 1 10\nnest 5 6 30\n").unwrap();
+        config.set_parameter("map_file", &path.to_string_lossy());
+
+        let nest = NestConfig::from_config(&config);
+        assert_eq!(nest.pos, Some((5, 6)));
+        assert_eq!(nest.radius, 30.0);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
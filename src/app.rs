@@ -1,14 +1,27 @@
+use crate::command::Command;
 use crate::config::Config;
-use crate::util::map_pos_to_grid;
+use crate::draw::draw_world;
+use crate::frame::Frame;
+use crate::inspector::Inspector;
+use crate::layers::{LayerSettings, RenderLayer};
+use crate::metrics::MetricsRecorder;
+use crate::overlay::Overlay;
+use crate::pacing::Pacing;
+use crate::reload::{changed_parameters, parameter_kind, ConfigWatcher, ParameterKind};
+use crate::renderer::{Corner, Renderer, SdlRenderer};
+use crate::replay::{Replay, ReplayEvent, ReplayPlayer};
+use crate::scenario::Scenario;
+use crate::tools::{Tool, ToolPalette};
+use crate::viewport::Viewport;
 use crate::world::World;
 use glam::DVec2;
-use sdl2::event::Event;
+use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
 
-use sdl2::pixels::Color;
-use sdl2::render::BlendMode;
+use sdl2::pixels::{Color, PixelFormatEnum};
 
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 pub struct App {
     config: Config,
@@ -28,28 +41,69 @@ impl App {
         return Self { config: conf };
     }
 
-    pub fn run(&self) {
-        let mut window_dimensions = (
-            self.config.get_parameter("win_size").vals[0] as u32,
-            self.config.get_parameter("win_size").vals[1] as u32,
+    pub fn config(&self) -> &Config {
+        return &self.config;
+    }
+
+    /// Opens the viewer. A replay is played back with the editing tools disabled,
+    /// with a record path the run is saved as a replay on exit. Scenario actions
+    /// are applied like edits, so recordings include them.
+    pub fn run(
+        &self,
+        replay: Option<Replay>,
+        record_path: Option<&str>,
+        mut scenario: Option<Scenario>,
+    ) {
+        let mut config = match replay.as_ref() {
+            Some(replay) => replay.config(),
+            None => self.config.clone(),
+        };
+
+        let window_dimensions = (
+            config.get_parameter("win_size").vals[0] as u32,
+            config.get_parameter("win_size").vals[1] as u32,
         );
 
         // * World setup
-        let world_grid_size = (
-            self.config.get_parameter("grid_dim").vals[0] as u32,
-            self.config.get_parameter("grid_dim").vals[1] as u32,
-        );
-        let mut world = World::new(
-            self.config.get_parameter("num_ants").vals[0] as u32,
-            world_grid_size,
-            &mut window_dimensions,
-            self.config.get_parameter("tile_size").vals[0],
-            Color::BLACK,
-            (
-                self.config.get_parameter("food_amount_range").vals[0] as u32,
-                self.config.get_parameter("food_amount_range").vals[1] as u32,
-            ),
-        );
+        let mut world = World::from_config(&config);
+        // Config the world was created from, structural edits after it wait for a reset
+        let mut world_config = config.clone();
+
+        // Replay playback or recording of this run
+        let mut player = replay.as_ref().map(ReplayPlayer::new);
+        let end_tick = replay.as_ref().map_or(0, |replay| replay.end_tick);
+        let mut recording = record_path.map(|_| Replay::new(world.seed(), &config));
+
+        // Edits to the config file apply while running, except in replays and recordings
+        // as those only store the config they started with
+        let mut watcher = if config.get_parameter("config_reload").vals[0] == 0.0 {
+            None
+        } else if player.is_some() || recording.is_some() {
+            log::info!("config reload is off while replaying or recording");
+            None
+        } else {
+            Some(ConfigWatcher::new(
+                Config::get_conf_path().join("config.ini"),
+                Duration::from_millis(500),
+            ))
+        };
+
+        // Metrics are only written when a file is configured
+        let metrics_file = config.get_words("metrics_file")[0].clone();
+        let mut recorder = if metrics_file != "none" {
+            match MetricsRecorder::create(
+                &metrics_file,
+                config.get_parameter("metrics_interval").vals[0] as u64,
+            ) {
+                Ok(recorder) => Some(recorder),
+                Err(e) => {
+                    log::error!("metrics error: {}", &e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
 
         // ! Graphics setup --
 
@@ -62,22 +116,43 @@ impl App {
         let window = video_subsystem
             .window("Ants 2.0", window_dimensions.0, window_dimensions.1)
             .position_centered()
+            .resizable()
             .build()
             .unwrap();
-        let mut win_canvas = window
+        let win_canvas = window
             .into_canvas()
             .build()
             .map_err(|e| e.to_string())
             .unwrap();
 
-        // Set window background
-        win_canvas.set_draw_color(Color::RGB(
-            self.config.get_parameter("background_color").vals[0] as u8,
-            self.config.get_parameter("background_color").vals[1] as u8,
-            self.config.get_parameter("background_color").vals[2] as u8,
-        ));
+        // Window background, the letterbox bars around the world stay black
+        let mut background_color = Color::RGB(
+            config.get_parameter("background_color").vals[0] as u8,
+            config.get_parameter("background_color").vals[1] as u8,
+            config.get_parameter("background_color").vals[2] as u8,
+        );
+
+        // Tiles go into one texture, scaled onto the world area in a single copy
+        let texture_creator = win_canvas.texture_creator();
+        let mut renderer = SdlRenderer::new(win_canvas, &texture_creator);
+
+        // Switchable visualisation layers
+        let mut layers = LayerSettings::new(
+            config.get_parameter("layer_marker_max").vals[0],
+            config.get_parameter("layer_food_max").vals[0],
+        );
+
+        // Statistics panel, toggled with tab
+        let mut overlay = Overlay::new(true);
+
+        // Hovered tile and selected ant
+        let mut inspector = Inspector::new();
+
+        // Mouse tools for editing the world
+        let mut tools = ToolPalette::new();
 
-        win_canvas.set_blend_mode(BlendMode::Blend);
+        // Maps world units to window pixels, updated on resize
+        let mut viewport = Viewport::new(world.world_size(), window_dimensions);
 
         // Event pump creation
         let mut event_pump = sdl_context.event_pump().unwrap();
@@ -85,8 +160,20 @@ impl App {
         // ! Graphics setup end --
 
         // Timing setup
-        const TIMING_TICK_TIME: u128 = 25;
-        let mut timing_tick_clock = Instant::now();
+        let mut pacing = Pacing::new();
+
+        // A replayed pause holds the ticks until the instant
+        let mut replay_hold: Option<Instant> = None;
+
+        // S saves the next presented frame
+        let mut save_frame = false;
+
+        // Changed parameters only a new world picks up, R resets it
+        let mut pending_reset: Vec<String> = Vec::new();
+
+        let mut fps_clock = Instant::now();
+        let mut frames_since_fps = 0;
+        let mut fps = 0;
 
         // Main loop
         'running: loop {
@@ -100,41 +187,391 @@ impl App {
                         keycode: Some(Keycode::Escape),
                         ..
                     } => break 'running,
-                    Event::MouseButtonDown { x, y, .. } => {
-                        world.insert_food(
-                            map_pos_to_grid(
-                                DVec2::new(x as f64, y as f64),
-                                world_grid_size,
-                                window_dimensions,
-                            ),
-                            2000,
-                        );
+                    Event::KeyDown {
+                        keycode: Some(keycode),
+                        ..
+                    } => {
+                        let layer_keys = [
+                            Keycode::Num1,
+                            Keycode::Num2,
+                            Keycode::Num3,
+                            Keycode::Num4,
+                            Keycode::Num5,
+                        ];
+
+                        if let Some(index) = layer_keys.iter().position(|k| *k == keycode) {
+                            layers.set_active(RenderLayer::ALL[index]);
+                            world.invalidate_tiles();
+                        } else {
+                            match keycode {
+                                Keycode::C => {
+                                    layers.cycle_color_map();
+                                    world.invalidate_tiles();
+                                }
+                                Keycode::L => {
+                                    layers.toggle_scaling();
+                                    world.invalidate_tiles();
+                                }
+                                Keycode::Tab => overlay.toggle(),
+                                Keycode::R if !pending_reset.is_empty() => {
+                                    world = World::from_config(&config);
+                                    world_config = config.clone();
+                                    pending_reset.clear();
+
+                                    viewport =
+                                        Viewport::new(world.world_size(), renderer.output_size());
+                                    inspector = Inspector::new();
+                                    if let Some(recorder) = recorder.as_mut() {
+                                        recorder.reset();
+                                    }
+                                    log::info!("world reset with the new config");
+                                }
+                                Keycode::S => save_frame = true,
+                                Keycode::Space => {
+                                    if let Some(paused_for) = pacing.toggle_pause() {
+                                        record_pause(&mut recording, &world, paused_for);
+                                    }
+                                }
+                                Keycode::Equals | Keycode::KpPlus => pacing.faster(),
+                                Keycode::Minus | Keycode::KpMinus => pacing.slower(),
+                                Keycode::I => tools.select(Tool::Inspect),
+                                Keycode::F => tools.select(Tool::FoodBrush),
+                                Keycode::W => tools.select(Tool::WallBrush),
+                                Keycode::E => tools.select(Tool::Eraser),
+                                Keycode::P => tools.select(Tool::PheromonePaint),
+                                Keycode::X => tools.select(Tool::PheromoneErase),
+                                Keycode::M => tools.select(Tool::MoveHill),
+                                Keycode::LeftBracket => tools.halve_amount(),
+                                Keycode::RightBracket => tools.double_amount(),
+                                _ => {}
+                            }
+                        }
+                    }
+                    Event::Window {
+                        win_event: WindowEvent::SizeChanged(w, h),
+                        ..
+                    } => {
+                        viewport.resize((w as u32, h as u32));
+                    }
+                    Event::MouseWheel { y, .. } => {
+                        tools.adjust_radius(y);
+                    }
+                    Event::MouseMotion {
+                        x, y, mousestate, ..
+                    } => {
+                        let world_pos = viewport.screen_to_world(DVec2::new(x as f64, y as f64));
+                        inspector.hover(&world, world_pos);
+
+                        // Dragging keeps applying the tool
+                        if let Some(world_pos) = world_pos {
+                            if mousestate.left() && player.is_none() {
+                                if let Some(command) =
+                                    tools.command_at(world.world_to_grid(world_pos))
+                                {
+                                    apply_command(&mut world, &mut recording, &command);
+                                }
+                            }
+                        }
+                    }
+                    Event::MouseButtonDown {
+                        mouse_btn: MouseButton::Right,
+                        ..
+                    } => {
+                        inspector.clear_selection();
+                    }
+                    Event::MouseButtonDown {
+                        mouse_btn: MouseButton::Left,
+                        x,
+                        y,
+                        ..
+                    } => {
+                        tools.begin_stroke();
+
+                        if let Some(world_pos) =
+                            viewport.screen_to_world(DVec2::new(x as f64, y as f64))
+                        {
+                            if tools.active() == Tool::Inspect {
+                                inspector.select_at(&world, world_pos);
+                            } else if player.is_some() {
+                                // Edits would make the replay diverge
+                            } else if let Some(command) =
+                                tools.command_at(world.world_to_grid(world_pos))
+                            {
+                                apply_command(&mut world, &mut recording, &command);
+                            }
+                        }
                     }
                     _ => {}
                 }
             }
 
+            // Config file edits
+            if let Some(new_config) = watcher.as_mut().and_then(|watcher| watcher.poll()) {
+                match reload_config(
+                    &config,
+                    new_config,
+                    &mut world,
+                    &mut layers,
+                    &mut background_color,
+                ) {
+                    Ok(new_config) => {
+                        config = new_config;
+                        pending_reset = changed_parameters(&world_config, &config)
+                            .into_iter()
+                            .filter(|name| parameter_kind(name) == ParameterKind::Structural)
+                            .collect();
+                        world.invalidate_tiles();
+                    }
+                    Err(e) => {
+                        log::error!("config reload error: {}", &e);
+                    }
+                }
+            }
+
             // Tick update
 
-            if timing_tick_clock.elapsed().as_millis() >= TIMING_TICK_TIME {
+            if let Some(until) = replay_hold {
+                if Instant::now() >= until {
+                    replay_hold = None;
+                }
+            }
+
+            let ticks = match replay_hold {
+                Some(_) => 0,
+                None => pacing.take_step(),
+            };
+
+            for _ in 0..ticks {
+                // Replayed edits go in before the tick they were recorded at
+                if let Some(player) = player.as_mut() {
+                    for event in player.due(world.tick()) {
+                        match event {
+                            ReplayEvent::Command(command) => match world.apply(&command) {
+                                Ok(_) => {}
+                                Err(e) => {
+                                    log::error!("replay error: {}", &e);
+                                }
+                            },
+                            ReplayEvent::Pause { millis } => {
+                                replay_hold = Some(Instant::now() + Duration::from_millis(millis));
+                            }
+                        }
+                    }
+                }
+                if replay_hold.is_some() {
+                    break;
+                }
+
+                if let Some(scenario) = scenario.as_mut() {
+                    for command in scenario.due(&world) {
+                        apply_command(&mut world, &mut recording, &command);
+                    }
+                }
+
                 world.update();
+                inspector.record(&world);
+
+                if let Some(recorder) = recorder.as_mut() {
+                    match recorder.record(&world) {
+                        Ok(_) => {}
+                        Err(e) => {
+                            log::error!("metrics error: {}", &e);
+                        }
+                    }
+                }
+
+                for event in world.drain_food_events() {
+                    log::info!("{:?}", event);
+                }
 
-                timing_tick_clock = Instant::now();
+                // The recording ends here, the rest would no longer be a replay
+                if player.is_some() && world.tick() == end_tick {
+                    log::info!("replay finished at tick {}", end_tick);
+                    pacing.pause();
+                    break;
+                }
             }
 
             // ! Update end --
 
             // * Render --
-            win_canvas.clear();
+            renderer.clear(Color::BLACK);
+
+            // Only tiles changed since the last frame get uploaded
+            let dirty = world.take_dirty_tiles();
+            draw_world(
+                &mut renderer,
+                &world,
+                dirty,
+                &viewport,
+                &layers,
+                background_color,
+            );
+
+            inspector.render(&mut renderer, &viewport, &world);
+
+            if overlay.is_visible() {
+                overlay.render(
+                    &mut renderer,
+                    &Overlay::stat_lines(&world.stats(), fps),
+                    Corner::TopLeft,
+                );
+                overlay.render(&mut renderer, &inspector.lines(&world), Corner::BottomLeft);
 
-            // Render
-            world.render(&mut win_canvas);
+                let mut tool_lines = tools.lines();
+                if player.is_some() {
+                    tool_lines.push(format!("replay {}/{}", world.tick(), end_tick));
+                }
+                tool_lines.push(pacing.label());
+                tool_lines.push(format!(
+                    "layer {} ({}, {})",
+                    layers.active().name(),
+                    layers.active_style().color_map.name(),
+                    layers.active_style().scaling.name()
+                ));
+                tool_lines.extend(reset_lines(&pending_reset));
+                overlay.render(&mut renderer, &tool_lines, Corner::TopRight);
+            } else {
+                // The prompt stays up with the panels hidden
+                renderer.draw_text(&reset_lines(&pending_reset), Corner::TopRight);
+            }
+
+            // Read back before presenting, the back buffer is undefined afterwards
+            if save_frame {
+                save_frame = false;
+
+                let path = format!("frame_{:08}.png", world.tick());
+                let size = renderer.output_size();
+                let result = renderer
+                    .canvas()
+                    .read_pixels(None, PixelFormatEnum::ABGR8888)
+                    .and_then(|pixels| Frame::from_pixels(size, pixels).save_png(&path));
+
+                match result {
+                    Ok(_) => {
+                        log::info!("frame saved to {}", path);
+                    }
+                    Err(e) => {
+                        log::error!("frame error: {}", &e);
+                    }
+                }
+            }
 
-            win_canvas.present();
+            renderer.present();
             // * Render end --
 
+            frames_since_fps += 1;
+            if fps_clock.elapsed().as_millis() >= 1000 {
+                fps = frames_since_fps;
+                frames_since_fps = 0;
+                fps_clock = Instant::now();
+            }
+
             // Delay
             std::thread::sleep(::std::time::Duration::new(0, 1_000_000_000u32 / 60));
         }
+
+        if let Some(recorder) = recorder.as_mut() {
+            match recorder.flush() {
+                Ok(_) => {}
+                Err(e) => {
+                    log::error!("metrics error: {}", &e);
+                }
+            }
+        }
+
+        if let Some(paused_for) = pacing.paused_for() {
+            record_pause(&mut recording, &world, paused_for);
+        }
+        if let (Some(recording), Some(path)) = (recording.as_mut(), record_path) {
+            recording.end_tick = world.tick();
+            match recording.save(path) {
+                Ok(_) => {
+                    log::info!("replay saved to {}", path);
+                }
+                Err(e) => {
+                    log::error!("replay error: {}", &e);
+                }
+            }
+        }
+    }
+}
+
+// Edits from the tools, recorded before the tick they were applied to runs
+fn apply_command(world: &mut World, recording: &mut Option<Replay>, command: &Command) {
+    if let Err(e) = world.apply(command) {
+        log::error!("edit error: {}", &e);
+        return;
+    }
+
+    if let Some(recording) = recording.as_mut() {
+        recording.record(world.tick(), ReplayEvent::Command(*command));
+    }
+}
+
+fn record_pause(recording: &mut Option<Replay>, world: &World, paused_for: Duration) {
+    if let Some(recording) = recording.as_mut() {
+        recording.record(
+            world.tick(),
+            ReplayEvent::Pause {
+                millis: paused_for.as_millis() as u64,
+            },
+        );
+    }
+}
+
+/// Applies the live and view parameters of a reloaded config and returns it. Nothing
+/// changes if one of them is missing or invalid, structural changes are left to the caller.
+fn reload_config(
+    config: &Config,
+    new_config: Config,
+    world: &mut World,
+    layers: &mut LayerSettings,
+    background_color: &mut Color,
+) -> Result<Config, String> {
+    let changed = changed_parameters(config, &new_config);
+    if let Some(name) = changed.iter().find(|name| !new_config.has_parameter(name)) {
+        return Err(format!("parameter '{}' was removed", name));
+    }
+
+    let background = new_config.get_values("background_color", 3)?;
+    let marker_max = new_config.get_values("layer_marker_max", 1)?[0];
+    let food_max = new_config.get_values("layer_food_max", 1)?[0];
+
+    if changed
+        .iter()
+        .any(|name| parameter_kind(name) == ParameterKind::Live)
+    {
+        world.apply_live_parameters(&new_config)?;
     }
+
+    *background_color = Color::RGB(
+        background[0] as u8,
+        background[1] as u8,
+        background[2] as u8,
+    );
+    layers.set_limits(marker_max, food_max);
+
+    for name in changed.iter() {
+        log::info!(
+            "{} changed to {} ({:?})",
+            name,
+            new_config.get_parameter(name).text,
+            parameter_kind(name)
+        );
+    }
+
+    return Ok(new_config);
+}
+
+/// Prompt for structural changes waiting for a reset
+fn reset_lines(pending_reset: &[String]) -> Vec<String> {
+    if pending_reset.is_empty() {
+        return Vec::new();
+    }
+
+    return vec![
+        format!("changed {}", pending_reset.join(", ")),
+        "r resets the world".to_string(),
+    ];
 }
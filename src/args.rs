@@ -0,0 +1,76 @@
+//! Command line flags of the subcommands, given as "--flag value"
+
+/// Value following a flag, e.g. "--ticks 5000"
+pub fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    return args
+        .iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1))
+        .map(|value| value.as_str());
+}
+
+/// Values of a flag given several times, e.g. "--param a=1..3 --param b=1,2"
+pub fn flag_values<'a>(args: &'a [String], flag: &str) -> Vec<&'a str> {
+    let mut values: Vec<&str> = Vec::new();
+
+    for (index, arg) in args.iter().enumerate() {
+        if arg == flag {
+            if let Some(value) = args.get(index + 1) {
+                values.push(value.as_str());
+            }
+        }
+    }
+
+    return values;
+}
+
+pub fn parse_flag<T: std::str::FromStr>(args: &[String], flag: &str, default: T) -> T {
+    return match flag_value(args, flag) {
+        Some(text) => match text.parse::<T>() {
+            Ok(value) => value,
+            Err(_) => {
+                log::error!("invalid value '{}' for {}", text, flag);
+                std::process::exit(-1);
+            }
+        },
+        None => default,
+    };
+}
+
+/// Size given as "WxH", e.g. "--frame-size 1280x720"
+pub fn parse_size(args: &[String], flag: &str, default: (u32, u32)) -> (u32, u32) {
+    let text = match flag_value(args, flag) {
+        Some(text) => text,
+        None => return default,
+    };
+
+    let parts: Vec<Option<u32>> = text.split('x').map(|part| part.parse().ok()).collect();
+    return match parts[..] {
+        [Some(w), Some(h)] if w > 0 && h > 0 => (w, h),
+        _ => {
+            log::error!("invalid value '{}' for {}", text, flag);
+            std::process::exit(-1);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_are_read_after_their_name() {
+        let args: Vec<String> = "--param a=1 --ticks 50 --param b=2 --frame-size 64x32 --out"
+            .split(' ')
+            .map(|arg| arg.to_string())
+            .collect();
+
+        assert_eq!(flag_value(&args, "--ticks"), Some("50"));
+        assert_eq!(flag_value(&args, "--out"), None);
+        assert_eq!(flag_values(&args, "--param"), vec!["a=1", "b=2"]);
+        assert_eq!(parse_flag(&args, "--ticks", 10u64), 50);
+        assert_eq!(parse_flag(&args, "--seeds", 3u64), 3);
+        assert_eq!(parse_size(&args, "--frame-size", (1, 1)), (64, 32));
+        assert_eq!(parse_size(&args, "--serve-size", (200, 200)), (200, 200));
+    }
+}
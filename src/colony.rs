@@ -1,96 +1,189 @@
 use glam::DVec2;
-use rand::{distributions::Uniform, prelude::Distribution};
-use sdl2::{pixels::Color, rect::Rect, render::Canvas, video::Window};
+use rand::rngs::StdRng;
+use sdl2::pixels::Color;
+
+use crate::{
+    ant::Ant,
+    ant_hill::AntHill,
+    config::Config,
+    food::FoodSources,
+    grid::Grid,
+    tile::Terrain,
+    traits::{AntTraits, TRAIT_NAMES},
+    util::map_pos_to_grid,
+};
+
+/// Energy and reproduction settings. A drain of 0 makes ants immortal, a birth cost of 0 disables births.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lifecycle {
+    pub energy: f64,
+    pub energy_drain: f64,
+    pub energy_per_food: f64,
+    pub birth_cost: u64,
+    pub max_ants: usize,
+}
 
-use crate::{ant::Ant, ant_hill::AntHill, tile::Tile};
+impl Lifecycle {
+    pub fn from_config(config: &Config) -> Self {
+        return Self {
+            energy: config.get_parameter("ant_energy").vals[0],
+            energy_drain: config.get_parameter("ant_energy_drain").vals[0],
+            energy_per_food: config.get_parameter("energy_per_food").vals[0],
+            birth_cost: config.get_parameter("ant_birth_cost").vals[0] as u64,
+            max_ants: config.get_parameter("max_ants").vals[0] as usize,
+        };
+    }
+}
 
 pub struct Colony {
     pub ants: Vec<Ant>,
     pub ant_hill: AntHill,
+    pub traits: AntTraits,
+    pub life: Lifecycle,
+
+    pub births: u64,
+    pub deaths: u64,
+    /// Food carried by ants that starved on a wall, it has no tile to go back to
+    pub food_lost: u64,
+    next_id: u64,
 
     ant_color: Color,
 }
 
 impl Colony {
+    /// Ants start spread around the rim of the hill
     pub fn new(
         num_ants: u32,
         color: Color,
-        spawn_area_a: (u32, u32),
-        spawn_area_b: (u32, u32),
+        ant_hill: AntHill,
+        traits: AntTraits,
+        life: Lifecycle,
+        random_gen: &mut StdRng,
     ) -> Self {
-        // ? Ant spawning
-        let mut temp_ants: Vec<Ant> = Vec::new();
-        let mut ant_average_pos = DVec2::default();
-
-        let mut random_gen = rand::thread_rng();
-
-        let random_range_x = Uniform::from(spawn_area_a.0..spawn_area_b.0);
-        let random_range_y = Uniform::from(spawn_area_a.1..spawn_area_b.1);
+        let mut ants: Vec<Ant> = Vec::new();
 
-        for _ in 0..num_ants {
-            let pos = DVec2::new(
-                random_range_x.sample(&mut random_gen) as f64,
-                random_range_y.sample(&mut random_gen) as f64,
-            );
-            temp_ants.push(Ant::new(pos));
-
-            ant_average_pos += pos;
+        for id in 0..num_ants {
+            let pos = ant_hill.spawn_pos(random_gen);
+            ants.push(Colony::create_ant(
+                id as u64, pos, &traits, &life, random_gen,
+            ));
         }
 
-        // ? Ant hill pos
+        return Self {
+            ants: ants,
+            ant_hill: ant_hill,
+            traits: traits,
+            life: life,
 
-        let mut colony = Self {
-            ants: temp_ants,
-            ant_hill: AntHill::new(ant_average_pos / num_ants as f64, 25.0),
+            births: 0,
+            deaths: 0,
+            food_lost: 0,
+            next_id: num_ants as u64,
 
             ant_color: color,
         };
-
-        colony.center_ants();
-
-        return colony;
     }
 
     pub fn update(
         &mut self,
-        win_dim: (u32, u32),
+        world_dim: (u32, u32),
         grid_size: (u32, u32),
-        world_tiles: &mut Vec<Vec<Tile>>,
-        food_coords: &mut Vec<(u32, u32)>,
+        world_tiles: &mut Grid,
+        food_sources: &mut FoodSources,
+        random_gen: &mut StdRng,
     ) {
         for ant in self.ants.iter_mut() {
-            ant.update(win_dim, grid_size, world_tiles, &self.ant_hill, food_coords);
+            ant.drain_energy(self.life.energy_drain);
+            ant.update(
+                world_dim,
+                grid_size,
+                world_tiles,
+                &mut self.ant_hill,
+                food_sources,
+                random_gen,
+            );
         }
-    }
-
-    pub fn render(&self, canvas: &mut Canvas<Window>) {
-        let previous_color = canvas.draw_color();
-        canvas.set_draw_color(self.ant_color);
 
-        self.render_ants(canvas);
-        self.ant_hill.render(canvas);
+        let num_ants = self.ants.len();
+        // Starved ants drop what they carried where they died
+        for ant in self.ants.iter().filter(|ant| !ant.is_alive()) {
+            let pos = map_pos_to_grid(ant.pos(), grid_size, world_dim);
+            if world_tiles.tile(pos).terrain == Terrain::Wall {
+                self.food_lost += ant.carrying() as u64;
+            } else {
+                food_sources.drop_food(world_tiles, pos, ant.carrying());
+            }
+        }
+        self.ants.retain(|ant| ant.is_alive());
+        self.deaths += (num_ants - self.ants.len()) as u64;
 
-        canvas.set_draw_color(previous_color);
+        self.spawn_births(world_dim, random_gen);
     }
 
-    fn render_ants(&self, canvas: &mut Canvas<Window>) {
-        let mut ant_rects: Vec<Rect> = Vec::new();
-
-        for ant in self.ants.iter() {
-            ant_rects.push(ant.get_render_target());
+    // New ants hatch at the hill as long as the stored food pays for them.
+    // A hill moved to the edge has entrances outside the world, ants hatch on the border then.
+    fn spawn_births(&mut self, world_dim: (u32, u32), random_gen: &mut StdRng) {
+        if self.life.birth_cost == 0 {
+            return;
         }
 
-        match canvas.fill_rects(&ant_rects) {
-            Ok(_) => {}
-            Err(e) => {
-                log::error!("render error: {}", &e);
-            }
+        while self.ant_hill.food_stored >= self.life.birth_cost
+            && self.ants.len() < self.life.max_ants
+        {
+            self.ant_hill.food_stored -= self.life.birth_cost;
+
+            let pos = self.ant_hill.spawn_pos(random_gen).clamp(
+                DVec2::ZERO,
+                DVec2::new(world_dim.0 as f64, world_dim.1 as f64),
+            );
+            let ant = Colony::create_ant(self.next_id, pos, &self.traits, &self.life, random_gen);
+            self.ants.push(ant);
+
+            self.next_id += 1;
+            self.births += 1;
         }
     }
 
-    pub fn center_ants(&mut self) {
+    fn create_ant(
+        id: u64,
+        pos: DVec2,
+        traits: &AntTraits,
+        life: &Lifecycle,
+        random_gen: &mut StdRng,
+    ) -> Ant {
+        let caste = traits.choose_caste(random_gen);
+        return Ant::new(
+            id,
+            pos,
+            caste,
+            &traits.castes[caste].traits,
+            life.energy,
+            random_gen,
+        );
+    }
+
+    /// Ants redraw the traits whose distribution changed, the others are kept
+    pub fn set_traits(&mut self, traits: AntTraits, random_gen: &mut StdRng) {
         for ant in self.ants.iter_mut() {
-            ant.set_pos(self.ant_hill.pos);
+            let (old, new) = match (
+                self.traits.castes.get(ant.caste()),
+                traits.castes.get(ant.caste()),
+            ) {
+                (Some(old), Some(new)) => (&old.traits, &new.traits),
+                _ => continue,
+            };
+
+            for name in TRAIT_NAMES.iter() {
+                if old.get(name) != new.get(name) {
+                    ant.set_trait(name, new.get(name).sample(random_gen));
+                }
+            }
         }
+
+        self.traits = traits;
+    }
+
+    pub fn ant_color(&self) -> Color {
+        return self.ant_color;
     }
 }
@@ -0,0 +1,154 @@
+/// Edits applied to the world from outside the simulation, e.g. by the mouse tools.
+/// Positions are grid coordinates, radii are in tiles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    PlaceFood {
+        pos: (u32, u32),
+        radius: u32,
+        amount: u32,
+    },
+    PlaceWall {
+        pos: (u32, u32),
+        radius: u32,
+    },
+    /// Removes food and walls
+    Erase {
+        pos: (u32, u32),
+        radius: u32,
+    },
+    /// m_type 1 is the to-home trail, 2 the to-food trail
+    PaintMarker {
+        pos: (u32, u32),
+        radius: u32,
+        m_type: u8,
+        strength: f64,
+    },
+    EraseMarkers {
+        pos: (u32, u32),
+        radius: u32,
+    },
+    MoveHill {
+        pos: (u32, u32),
+    },
+    /// Walls on every tile between the two ends
+    PlaceWallLine {
+        from: (u32, u32),
+        to: (u32, u32),
+    },
+    /// Removes the food source closest to pos
+    RemoveNearestFood {
+        pos: (u32, u32),
+    },
+}
+
+impl Command {
+    /// Text form used by replay and scenario files, e.g. "place_food 10 12 2 2000"
+    pub fn to_line(&self) -> String {
+        match *self {
+            Command::PlaceFood {
+                pos,
+                radius,
+                amount,
+            } => format!("place_food {} {} {} {}", pos.0, pos.1, radius, amount),
+            Command::PlaceWall { pos, radius } => {
+                format!("place_wall {} {} {}", pos.0, pos.1, radius)
+            }
+            Command::Erase { pos, radius } => format!("erase {} {} {}", pos.0, pos.1, radius),
+            Command::PaintMarker {
+                pos,
+                radius,
+                m_type,
+                strength,
+            } => format!(
+                "paint_marker {} {} {} {} {}",
+                pos.0, pos.1, radius, m_type, strength
+            ),
+            Command::EraseMarkers { pos, radius } => {
+                format!("erase_markers {} {} {}", pos.0, pos.1, radius)
+            }
+            Command::MoveHill { pos } => format!("move_hill {} {}", pos.0, pos.1),
+            Command::PlaceWallLine { from, to } => {
+                format!("place_wall_line {} {} {} {}", from.0, from.1, to.0, to.1)
+            }
+            Command::RemoveNearestFood { pos } => {
+                format!("remove_nearest_food {} {}", pos.0, pos.1)
+            }
+        }
+    }
+
+    /// Grid positions the command touches, radii aside
+    pub fn positions(&self) -> Vec<(u32, u32)> {
+        return match *self {
+            Command::PlaceFood { pos, .. }
+            | Command::PlaceWall { pos, .. }
+            | Command::Erase { pos, .. }
+            | Command::PaintMarker { pos, .. }
+            | Command::EraseMarkers { pos, .. }
+            | Command::MoveHill { pos }
+            | Command::RemoveNearestFood { pos } => vec![pos],
+            Command::PlaceWallLine { from, to } => vec![from, to],
+        };
+    }
+
+    /// Reads the words written by `to_line`
+    pub fn parse(words: &[&str]) -> Result<Self, String> {
+        let line = words.join(" ");
+        let number = |index: usize| -> Result<f64, String> {
+            return match words.get(index).map(|word| word.parse::<f64>()) {
+                Some(Ok(value)) if value >= 0.0 => Ok(value),
+                _ => Err(format!("invalid or missing value {} in '{}'", index, line)),
+            };
+        };
+
+        let name = match words.first() {
+            Some(name) => *name,
+            None => return Err("empty command".to_string()),
+        };
+
+        let expected_len = match name {
+            "place_food" => 5,
+            "paint_marker" => 6,
+            "move_hill" | "remove_nearest_food" => 3,
+            "place_wall_line" => 5,
+            _ => 4,
+        };
+        if words.len() != expected_len {
+            return Err(format!("wrong number of values in '{}'", line));
+        }
+
+        let pos = (number(1)? as u32, number(2)? as u32);
+
+        return match name {
+            "place_food" => Ok(Command::PlaceFood {
+                pos: pos,
+                radius: number(3)? as u32,
+                amount: number(4)? as u32,
+            }),
+            "place_wall" => Ok(Command::PlaceWall {
+                pos: pos,
+                radius: number(3)? as u32,
+            }),
+            "erase" => Ok(Command::Erase {
+                pos: pos,
+                radius: number(3)? as u32,
+            }),
+            "paint_marker" => Ok(Command::PaintMarker {
+                pos: pos,
+                radius: number(3)? as u32,
+                m_type: number(4)? as u8,
+                strength: number(5)?,
+            }),
+            "erase_markers" => Ok(Command::EraseMarkers {
+                pos: pos,
+                radius: number(3)? as u32,
+            }),
+            "move_hill" => Ok(Command::MoveHill { pos: pos }),
+            "place_wall_line" => Ok(Command::PlaceWallLine {
+                from: pos,
+                to: (number(3)? as u32, number(4)? as u32),
+            }),
+            "remove_nearest_food" => Ok(Command::RemoveNearestFood { pos: pos }),
+            _ => Err(format!("unknown command '{}'", name)),
+        };
+    }
+}
@@ -10,6 +10,8 @@ pub struct ConfigParameter {
 pub struct Config {
     pub content: String,
     pub parameters: HashMap<String, ConfigParameter>,
+    // Contents given with set_file, read instead of the files they stand for
+    files: HashMap<String, String>,
 }
 
 impl Config {
@@ -26,9 +28,15 @@ impl Config {
             }
         }
 
+        return Config::parse(&content);
+    }
+
+    /// Config from the text of a config file
+    pub fn parse(content: &str) -> Self {
         let mut conf = Config {
-            content: content,
+            content: content.to_string(),
             parameters: HashMap::new(),
+            files: HashMap::new(),
         };
 
         conf.index();
@@ -41,42 +49,67 @@ impl Config {
             if line != "" {
                 let param_body: Vec<&str> = line.split(" ").collect();
 
+                if param_body.len() < 2 {
+                    log::error!("config line '{}' has no value", &line);
+                    continue;
+                }
+
                 let param_name = param_body[0];
                 let param_conf = param_body[1];
 
-                let mut param_values: Vec<f64> = Vec::new();
-
-                for param_val_txt in param_conf
-                    .replace(" ", "")
-                    .split(",")
-                    .collect::<Vec<&str>>()
-                {
-                    let mut param = 0.0;
-                    let parsed_param = param_val_txt.parse::<f64>();
-
-                    match parsed_param {
-                        Ok(_) => {
-                            param = parsed_param.unwrap();
-                        }
-                        Err(_) => {
-                            log::error!("failed to parse '{}' as config parameter", &line);
-                        }
-                    }
-
-                    param_values.push(param);
-                }
-
                 self.parameters.insert(
                     param_name.to_string(),
                     ConfigParameter {
                         text: param_conf.to_string(),
-                        vals: param_values,
+                        vals: Config::parse_values(param_conf, line),
                     },
                 );
             }
         }
     }
 
+    fn parse_values(param_conf: &str, line: &str) -> Vec<f64> {
+        let mut param_values: Vec<f64> = Vec::new();
+
+        for param_val_txt in param_conf
+            .replace(" ", "")
+            .split(",")
+            .collect::<Vec<&str>>()
+        {
+            let parsed_param = param_val_txt.parse::<f64>();
+
+            match parsed_param {
+                Ok(_) => {
+                    param_values.push(parsed_param.unwrap());
+                }
+                Err(_) => {
+                    // Words like generator names or file paths are only available as text
+                    if !param_val_txt.starts_with(char::is_alphabetic) {
+                        log::error!("failed to parse '{}' as config parameter", &line);
+                        param_values.push(0.0);
+                    }
+                }
+            }
+        }
+
+        return param_values;
+    }
+
+    /// Replaces a parameter as if the line "name value" was in the file
+    pub fn set_parameter(&mut self, name: &str, value: &str) {
+        self.parameters.insert(
+            name.to_string(),
+            ConfigParameter {
+                text: value.to_string(),
+                vals: Config::parse_values(value, &format!("{} {}", name, value)),
+            },
+        );
+    }
+
+    pub fn has_parameter(&self, name: &str) -> bool {
+        return self.parameters.contains_key(name);
+    }
+
     pub fn get_parameter(&self, name: &str) -> ConfigParameter {
         if self.parameters.contains_key(name) {
             return self.parameters[name].clone();
@@ -86,9 +119,101 @@ impl Config {
         }
     }
 
+    /// Numbers of a parameter, an error instead of exiting if it is missing or too short
+    pub fn get_values(&self, name: &str, count: usize) -> Result<Vec<f64>, String> {
+        return match self.parameters.get(name) {
+            Some(parameter) if parameter.vals.len() >= count => Ok(parameter.vals.clone()),
+            Some(parameter) => Err(format!(
+                "'{} {}' needs {} numbers",
+                name, parameter.text, count
+            )),
+            None => Err(format!("parameter '{}' not found", name)),
+        };
+    }
+
+    /// Comma separated words of a parameter, e.g. "normal,5,1" gives ["normal", "5", "1"]
+    pub fn get_words(&self, name: &str) -> Vec<String> {
+        return self
+            .get_parameter(name)
+            .text
+            .split(",")
+            .map(|word| word.trim().to_string())
+            .collect();
+    }
+
+    /// Config file text of the current parameters, including ones changed with set_parameter
+    pub fn to_text(&self) -> String {
+        let mut names: Vec<&String> = self.parameters.keys().collect();
+        names.sort();
+
+        let mut text = String::new();
+        for name in names {
+            text.push_str(&format!("{} {}\n", name, self.parameters[name].text));
+        }
+
+        return text;
+    }
+
+    /// Contents of a file the config refers to, relative to the data folder
+    pub fn read_file(&self, path: &str) -> Result<String, String> {
+        if let Some(content) = self.files.get(path) {
+            return Ok(content.clone());
+        }
+
+        return fs::read_to_string(Config::get_conf_path().join(path)).map_err(|e| e.to_string());
+    }
+
+    /// Makes read_file return content for path instead of reading it, e.g. the map of a replay
+    pub fn set_file(&mut self, path: &str, content: &str) {
+        self.files.insert(path.to_string(), content.to_string());
+    }
+
     pub fn get_conf_path() -> PathBuf {
         return find_folder::Search::KidsThenParents(5, 5)
             .for_folder("data")
             .expect("data folder not found");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_all_value_forms() {
+        let config = Config::parse(
+            "num_ants 1000\n\
+             win_size 800,600\n\
+             rate -0.25\n\
+             food_layout clusters\n\
+             ant_speed normal,5,1.5\n\
+             map_file maps/test.map\n\
+             \n\
+             broken\n",
+        );
+
+        assert_eq!(config.get_parameter("num_ants").vals, vec![1000.0]);
+        assert_eq!(config.get_parameter("win_size").vals, vec![800.0, 600.0]);
+        assert_eq!(config.get_parameter("rate").vals, vec![-0.25]);
+
+        // Words are kept as text and left out of the numbers
+        assert!(config.get_parameter("food_layout").vals.is_empty());
+        assert_eq!(config.get_words("food_layout"), vec!["clusters"]);
+        assert_eq!(config.get_parameter("ant_speed").vals, vec![5.0, 1.5]);
+        assert_eq!(config.get_words("ant_speed"), vec!["normal", "5", "1.5"]);
+        assert_eq!(config.get_words("map_file"), vec!["maps/test.map"]);
+
+        assert!(!config.has_parameter("broken"));
+    }
+
+    #[test]
+    fn set_parameter_replaces_values() {
+        let mut config = Config::parse("num_ants 1000\n");
+
+        config.set_parameter("num_ants", "250");
+        config.set_parameter("seed", "random");
+
+        assert_eq!(config.get_parameter("num_ants").vals, vec![250.0]);
+        assert_eq!(config.get_words("seed"), vec!["random"]);
+    }
+}
@@ -0,0 +1,171 @@
+use glam::DVec2;
+use sdl2::{pixels::Color, rect::Rect};
+
+use crate::{
+    ant::Ant,
+    ant_hill::{AntHill, ENTRANCE_SIZE},
+    grid::DirtyRegion,
+    layers::LayerSettings,
+    renderer::{Heatmap, Renderer},
+    viewport::Viewport,
+    world::World,
+};
+
+/// Background, tiles, ants and the hill. Pass the tiles changed since the last frame
+/// as dirty, backends that redraw everything ignore it.
+pub fn draw_world<R: Renderer>(
+    renderer: &mut R,
+    world: &World,
+    dirty: Option<DirtyRegion>,
+    viewport: &Viewport,
+    layers: &LayerSettings,
+    background: Color,
+) {
+    draw_tiles(renderer, world, dirty, viewport, layers, background);
+
+    let ant_rects: Vec<Rect> = world
+        .ants()
+        .iter()
+        .map(|ant| ant_rect(ant, viewport))
+        .collect();
+    renderer.fill_rects(&ant_rects, world.ant_color());
+
+    draw_ant_hill(renderer, world.ant_hill(), viewport);
+}
+
+/// Background and the active layer of the tiles
+pub fn draw_tiles<R: Renderer>(
+    renderer: &mut R,
+    world: &World,
+    dirty: Option<DirtyRegion>,
+    viewport: &Viewport,
+    layers: &LayerSettings,
+    background: Color,
+) {
+    renderer.fill_rects(&[viewport.world_rect()], background);
+
+    renderer.draw_heatmap(
+        &Heatmap {
+            grid: world.grid(),
+            layers: layers,
+            dirty: dirty,
+        },
+        viewport.world_rect(),
+    );
+}
+
+pub fn ant_rect(ant: &Ant, viewport: &Viewport) -> Rect {
+    return viewport.map_rect(
+        ant.pos().x - ant.size() / 2.0,
+        ant.pos().y - ant.size() / 2.0,
+        ant.size(),
+        ant.size(),
+    );
+}
+
+pub fn draw_ant_hill<R: Renderer>(renderer: &mut R, ant_hill: &AntHill, viewport: &Viewport) {
+    let color = ant_hill.color();
+    let radius = ant_hill.radius();
+
+    renderer.fill_rects(
+        &disc_rects(
+            viewport.world_to_screen(ant_hill.pos),
+            radius * viewport.scale(),
+        ),
+        color,
+    );
+
+    // Entrances are drawn as dark notches in the rim
+    let entrance_size = radius * ENTRANCE_SIZE;
+    let entrance_rects: Vec<Rect> = ant_hill
+        .entrance_positions()
+        .iter()
+        .map(|entrance| {
+            viewport.map_rect(
+                entrance.x - entrance_size / 2.0,
+                entrance.y - entrance_size / 2.0,
+                entrance_size,
+                entrance_size,
+            )
+        })
+        .collect();
+
+    renderer.fill_rects(
+        &entrance_rects,
+        Color::RGB(color.r / 3, color.g / 3, color.b / 3),
+    );
+}
+
+/// Filled circle as one rect per pixel row, at least the center pixel
+fn disc_rects(center: DVec2, radius: f64) -> Vec<Rect> {
+    let mut rects: Vec<Rect> = Vec::new();
+
+    let top = (center.y - radius).floor() as i32;
+    let bottom = (center.y + radius).ceil() as i32;
+    for y in top..bottom {
+        let dy = y as f64 + 0.5 - center.y;
+        if dy.abs() > radius {
+            continue;
+        }
+
+        let half_width = (radius * radius - dy * dy).sqrt();
+        let left = (center.x - half_width).round() as i32;
+        let right = (center.x + half_width).round() as i32;
+        rects.push(Rect::new(left, y, (right - left).max(1) as u32, 1));
+    }
+
+    if rects.is_empty() {
+        rects.push(Rect::new(center.x as i32, center.y as i32, 1, 1));
+    }
+
+    return rects;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{command::Command, fixture, frame::Frame, layers::WALL_COLOR};
+
+    #[test]
+    fn draws_walls_and_the_nest_into_a_frame() {
+        let mut world = fixture::world();
+        world
+            .apply(&Command::PlaceWall {
+                pos: (2, 2),
+                radius: 0,
+            })
+            .unwrap();
+
+        // 40x40 tiles onto 80x80 pixels, the nest sits in tile 20,20
+        let mut frame = Frame::new((80, 80), Color::BLACK);
+        let viewport = Viewport::new(world.world_size(), frame.size());
+        draw_world(
+            &mut frame,
+            &world,
+            None,
+            &viewport,
+            &LayerSettings::new(1000.0, 100.0),
+            Color::RGB(10, 10, 10),
+        );
+
+        assert_eq!(frame.pixel(5, 5), WALL_COLOR);
+        assert_eq!(frame.pixel(41, 41), Color::RGB(200, 80, 5));
+        assert_eq!(frame.pixel(70, 10), Color::RGB(10, 10, 10));
+    }
+
+    #[test]
+    fn the_nest_is_round() {
+        let world = fixture::world();
+
+        // Nest of radius 12.5 around 205,205 drawn at scale 1
+        let mut frame = Frame::new((400, 400), Color::BLACK);
+        let viewport = Viewport::new(world.world_size(), frame.size());
+        draw_ant_hill(&mut frame, world.ant_hill(), &viewport);
+
+        assert_eq!(frame.pixel(205, 194), Color::RGB(200, 80, 5));
+        assert_eq!(frame.pixel(194, 205), Color::RGB(200, 80, 5));
+        assert_eq!(frame.pixel(212, 212), Color::RGB(200, 80, 5));
+        assert_eq!(frame.pixel(194, 194), Color::BLACK);
+        assert_eq!(frame.pixel(216, 216), Color::BLACK);
+    }
+}
@@ -0,0 +1,198 @@
+use std::path::Path;
+
+use sdl2::{pixels::Color, rect::Rect};
+
+use crate::{
+    config::Config,
+    draw::draw_world,
+    frame::{Animation, Frame},
+    layers::{LayerSettings, Scaling, WALL_COLOR},
+    renderer::Renderer,
+    tile::Terrain,
+    viewport::Viewport,
+    world::World,
+};
+
+/// Where and how often headless runs write images of the world
+pub struct ExportSettings {
+    /// Folder for numbered PNG frames
+    pub frame_dir: Option<String>,
+    /// Animated PNG of all captured frames
+    pub animation_path: Option<String>,
+    pub frame_every: u64,
+    pub frame_size: (u32, u32),
+    pub frame_millis: u16,
+}
+
+/// Draws the world offscreen every `frame_every` ticks, including tick 0
+pub struct FrameExport {
+    settings: ExportSettings,
+    animation: Option<Animation>,
+
+    viewport: Viewport,
+    layers: LayerSettings,
+    background: Color,
+}
+
+impl FrameExport {
+    /// Expects the world before its first tick, ticks is how long it will run
+    pub fn create(
+        settings: ExportSettings,
+        config: &Config,
+        world: &World,
+        ticks: u64,
+    ) -> Result<Self, String> {
+        if let Some(dir) = settings.frame_dir.as_ref() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir, e))?;
+        }
+
+        let animation = match settings.animation_path.as_ref() {
+            Some(path) => Some(Animation::create(
+                path,
+                settings.frame_size,
+                (ticks / settings.frame_every) as u32 + 1,
+                settings.frame_millis,
+            )?),
+            None => None,
+        };
+
+        return Ok(Self {
+            viewport: Viewport::new(world.world_size(), settings.frame_size),
+            layers: LayerSettings::new(
+                config.get_parameter("layer_marker_max").vals[0],
+                config.get_parameter("layer_food_max").vals[0],
+            ),
+            background: Color::RGB(
+                config.get_parameter("background_color").vals[0] as u8,
+                config.get_parameter("background_color").vals[1] as u8,
+                config.get_parameter("background_color").vals[2] as u8,
+            ),
+
+            settings: settings,
+            animation: animation,
+        });
+    }
+
+    pub fn capture(&mut self, world: &World) -> Result<(), String> {
+        if world.tick() % self.settings.frame_every != 0 {
+            return Ok(());
+        }
+
+        // Letterbox bars stay black like in the window
+        let mut frame = Frame::new(self.settings.frame_size, Color::BLACK);
+        draw_world(
+            &mut frame,
+            world,
+            None,
+            &self.viewport,
+            &self.layers,
+            self.background,
+        );
+
+        if let Some(dir) = self.settings.frame_dir.as_ref() {
+            let path = Path::new(dir).join(format!("frame_{:08}.png", world.tick()));
+            frame.save_png(&path.to_string_lossy())?;
+        }
+        if let Some(animation) = self.animation.as_mut() {
+            animation.add_frame(&frame)?;
+        }
+
+        return Ok(());
+    }
+
+    pub fn finish(self) -> Result<(), String> {
+        return match self.animation {
+            Some(animation) => animation.finish(),
+            None => Ok(()),
+        };
+    }
+}
+
+/// One pixel per tile, to-home trails in red and to-food trails in blue
+pub fn pheromone_image(world: &World, marker_max: f64) -> Frame {
+    let mut frame = Frame::new(world.grid_size(), Color::BLACK);
+
+    for (x, column) in world.grid().tiles().iter().enumerate() {
+        for (y, tile) in column.iter().enumerate() {
+            let color = if tile.terrain == Terrain::Wall {
+                WALL_COLOR
+            } else {
+                Color::RGB(
+                    (Scaling::Log.apply(tile.to_home_strength(), marker_max) * 255.0) as u8,
+                    0,
+                    (Scaling::Log.apply(tile.to_food_strength(), marker_max) * 255.0) as u8,
+                )
+            };
+
+            frame.fill_rects(&[Rect::new(x as i32, y as i32, 1, 1)], color);
+        }
+    }
+
+    return frame;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    fn decoded_frames(path: &str) -> (u32, u32) {
+        let decoder = png::Decoder::new(std::fs::File::open(path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let announced = reader.info().animation_control.unwrap().num_frames;
+
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let mut decoded = 0;
+        while reader.next_frame(&mut buffer).is_ok() {
+            decoded += 1;
+        }
+
+        return (announced, decoded);
+    }
+
+    fn export(ticks: u64, ticks_run: u64) -> (u32, u32) {
+        let path = std::env::temp_dir().join(format!(
+            "ants_export_{}_{}.png",
+            std::process::id(),
+            ticks_run
+        ));
+        let path = path.to_string_lossy().to_string();
+
+        let mut config = fixture::config();
+        config.set_parameter("layer_marker_max", "1000");
+        config.set_parameter("layer_food_max", "100");
+        config.set_parameter("background_color", "10,10,10");
+
+        let mut world = fixture::world();
+        let settings = ExportSettings {
+            frame_dir: None,
+            animation_path: Some(path.clone()),
+            frame_every: 5,
+            frame_size: (40, 30),
+            frame_millis: 50,
+        };
+        let mut export = FrameExport::create(settings, &config, &world, ticks).unwrap();
+
+        export.capture(&world).unwrap();
+        for _ in 0..ticks_run {
+            world.update();
+            export.capture(&world).unwrap();
+        }
+        export.finish().unwrap();
+
+        let frames = decoded_frames(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        return frames;
+    }
+
+    #[test]
+    fn animation_holds_a_frame_per_capture() {
+        assert_eq!(export(20, 20), (5, 5));
+    }
+
+    #[test]
+    fn animation_of_a_run_that_stopped_early_is_complete() {
+        assert_eq!(export(20, 7), (5, 5));
+    }
+}
@@ -0,0 +1,12 @@
+//! Small deterministic world for tests: a 40x40 grid, one ant with fixed traits
+//! on the rim of a nest in the middle, no food and no energy drain.
+
+use crate::{config::Config, world::World};
+
+pub fn config() -> Config {
+    return Config::parse(include_str!("../tests/fixtures/world.ini"));
+}
+
+pub fn world() -> World {
+    return World::from_config(&config());
+}
@@ -1,4 +1,443 @@
+use crate::{food_index::FoodIndex, grid::Grid};
+
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Food {
     pub concentration: u32,
+    /// Id of the food source this tile belongs to
+    pub source: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FoodEvent {
+    Depleted { source: usize, tick: u64 },
+    Replenished { source: usize, tick: u64 },
+}
+
+/// Patch of food spread over the tiles within radius of pos. The tiles hold the
+/// actual food, the source keeps the totals in sync with them.
+#[derive(Debug, Clone)]
+pub struct FoodSource {
+    pub id: usize,
+    pub pos: (u32, u32),
+    pub radius: u32,
+
+    pub capacity: u64,
+    pub amount: u64,
+    /// Food per tick added back until capacity is reached
+    pub regrowth_rate: f64,
+    pub harvested: u64,
+
+    tiles: Vec<(u32, u32)>,
+    regrowth_progress: f64,
+    next_regrowth_tile: usize,
+}
+
+impl FoodSource {
+    pub fn is_depleted(&self) -> bool {
+        return self.amount == 0;
+    }
+
+    pub fn tiles(&self) -> &[(u32, u32)] {
+        return &self.tiles;
+    }
+
+    // Regrowth fills each tile up to an even share of the capacity
+    fn tile_capacity(&self) -> u32 {
+        if self.tiles.is_empty() {
+            return 0;
+        }
+        return ((self.capacity + self.tiles.len() as u64 - 1) / self.tiles.len() as u64) as u32;
+    }
+}
+
+/// Owns all writes to tile food, so the index always matches the tiles
+#[derive(Default)]
+pub struct FoodSources {
+    sources: Vec<FoodSource>,
+    index: FoodIndex,
+    events: Vec<FoodEvent>,
+    tick: u64,
+
+    // Food ever put on tiles and taken off them other than by harvesting
+    added: u64,
+    removed: u64,
+}
+
+impl FoodSources {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    pub fn get(&self, id: usize) -> Option<&FoodSource> {
+        return self.sources.get(id);
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, FoodSource> {
+        return self.sources.iter();
+    }
+
+    /// Tiles with food on them
+    pub fn index(&self) -> &FoodIndex {
+        return &self.index;
+    }
+
+    pub fn total_added(&self) -> u64 {
+        return self.added;
+    }
+
+    pub fn total_removed(&self) -> u64 {
+        return self.removed;
+    }
+
+    pub fn set_tick(&mut self, tick: u64) {
+        self.tick = tick;
+    }
+
+    /// Returns the events since the last call
+    pub fn drain_events(&mut self) -> Vec<FoodEvent> {
+        return self.events.drain(..).collect();
+    }
+
+    /// Spreads amount evenly over the given tiles, detaching them from any previous source.
+    /// Returns None if there are no tiles to put food on.
+    pub fn add(
+        &mut self,
+        grid: &mut Grid,
+        pos: (u32, u32),
+        radius: u32,
+        tiles: Vec<(u32, u32)>,
+        amount: u64,
+        regrowth_rate: f64,
+    ) -> Option<usize> {
+        if tiles.is_empty() {
+            return None;
+        }
+
+        let id = self.sources.len();
+        let share = amount / tiles.len() as u64;
+        let remainder = amount % tiles.len() as u64;
+
+        for (index, tile_pos) in tiles.iter().enumerate() {
+            self.remove_tile(grid, *tile_pos);
+
+            let extra = if (index as u64) < remainder { 1 } else { 0 };
+            let concentration = (share + extra) as u32;
+            grid.tile_mut(*tile_pos).food = Food {
+                concentration: concentration,
+                source: Some(id),
+            };
+
+            if concentration > 0 {
+                self.index.insert(*tile_pos);
+            }
+        }
+
+        self.added += amount;
+
+        self.sources.push(FoodSource {
+            id: id,
+            pos: pos,
+            radius: radius,
+
+            capacity: amount,
+            amount: amount,
+            regrowth_rate: regrowth_rate,
+            harvested: 0,
+
+            tiles: tiles,
+            regrowth_progress: 0.0,
+            next_regrowth_tile: 0,
+        });
+
+        return Some(id);
+    }
+
+    /// Clears the food on a tile and takes it out of its source
+    pub fn remove_tile(&mut self, grid: &mut Grid, pos: (u32, u32)) {
+        let food = grid.tile(pos).food;
+
+        if let Some(id) = food.source {
+            let source = &mut self.sources[id];
+            source.amount -= food.concentration as u64;
+            source.capacity -= source.capacity.min(source.tile_capacity() as u64);
+            source.tiles.retain(|tile_pos| *tile_pos != pos);
+            source.next_regrowth_tile = 0;
+        }
+
+        if food.concentration != 0 || food.source.is_some() {
+            grid.tile_mut(pos).food = Food::default();
+        }
+        self.removed += food.concentration as u64;
+        self.index.remove(pos);
+    }
+
+    /// Clears every tile of a source and stops its regrowth, the source stays as an empty entry
+    pub fn remove_source(&mut self, grid: &mut Grid, id: usize) {
+        let tiles = match self.sources.get(id) {
+            Some(source) => source.tiles.clone(),
+            None => return,
+        };
+        for pos in tiles {
+            self.remove_tile(grid, pos);
+        }
+
+        let source = &mut self.sources[id];
+        source.capacity = 0;
+        source.regrowth_rate = 0.0;
+        source.regrowth_progress = 0.0;
+    }
+
+    /// Puts food taken off the tiles back, e.g. what a starved ant carried.
+    /// On a tile of a source it counts towards that source again.
+    pub fn drop_food(&mut self, grid: &mut Grid, pos: (u32, u32), amount: u32) {
+        if amount == 0 {
+            return;
+        }
+
+        let tile = grid.tile_mut(pos);
+        tile.food.concentration += amount;
+        if let Some(id) = tile.food.source {
+            self.sources[id].amount += amount as u64;
+        }
+
+        self.index.insert(pos);
+    }
+
+    /// Takes one unit of food from the tile, returns false if there was none
+    pub fn harvest(&mut self, grid: &mut Grid, pos: (u32, u32)) -> bool {
+        let tile = grid.tile_mut(pos);
+        if !tile.sub_food() {
+            return false;
+        }
+
+        if tile.food.concentration == 0 {
+            self.index.remove(pos);
+        }
+
+        if let Some(id) = tile.food.source {
+            let source = &mut self.sources[id];
+            source.amount -= 1;
+            source.harvested += 1;
+
+            if source.is_depleted() {
+                self.events.push(FoodEvent::Depleted {
+                    source: id,
+                    tick: self.tick,
+                });
+            }
+        }
+
+        return true;
+    }
+
+    /// Adds back food to sources with a regrowth rate
+    pub fn regrow(&mut self, grid: &mut Grid) {
+        for source in self.sources.iter_mut() {
+            if source.regrowth_rate <= 0.0 || source.tiles.is_empty() {
+                continue;
+            }
+
+            if source.amount >= source.capacity {
+                source.regrowth_progress = 0.0;
+                continue;
+            }
+
+            source.regrowth_progress += source.regrowth_rate;
+            let was_depleted = source.is_depleted();
+            let tile_capacity = source.tile_capacity();
+
+            // Round robin over the tiles, so regrowth spreads evenly over the patch
+            let mut tiles_checked = 0;
+            while source.regrowth_progress >= 1.0
+                && source.amount < source.capacity
+                && tiles_checked < source.tiles.len()
+            {
+                let tile_pos = source.tiles[source.next_regrowth_tile];
+                source.next_regrowth_tile = (source.next_regrowth_tile + 1) % source.tiles.len();
+
+                if grid.tile(tile_pos).food.concentration >= tile_capacity {
+                    tiles_checked += 1;
+                    continue;
+                }
+                tiles_checked = 0;
+
+                let tile = grid.tile_mut(tile_pos);
+                if tile.food.concentration == 0 {
+                    self.index.insert(tile_pos);
+                }
+                tile.food.concentration += 1;
+
+                source.amount += 1;
+                self.added += 1;
+                source.regrowth_progress -= 1.0;
+            }
+
+            // Don't bank growth while every tile is full
+            source.regrowth_progress = source.regrowth_progress.min(1.0);
+
+            if was_depleted && !source.is_depleted() {
+                self.events.push(FoodEvent::Replenished {
+                    source: source.id,
+                    tick: self.tick,
+                });
+            }
+        }
+    }
+
+    /// Checks that the index holds exactly the tiles with food and that
+    /// every source's amount matches its tiles
+    pub fn check_invariants(&self, grid: &Grid) -> Result<(), String> {
+        let mut source_totals: Vec<u64> = vec![0; self.sources.len()];
+        let mut food_tiles = 0;
+
+        for (x, column) in grid.tiles().iter().enumerate() {
+            for (y, tile) in column.iter().enumerate() {
+                let pos = (x as u32, y as u32);
+                let has_food = tile.food.concentration > 0;
+
+                if has_food != self.index.contains(pos) {
+                    return Err(format!(
+                        "food index out of sync at {:?}: concentration {}, indexed {}",
+                        pos,
+                        tile.food.concentration,
+                        self.index.contains(pos)
+                    ));
+                }
+
+                if has_food {
+                    food_tiles += 1;
+                }
+
+                if let Some(id) = tile.food.source {
+                    match source_totals.get_mut(id) {
+                        Some(total) => *total += tile.food.concentration as u64,
+                        None => return Err(format!("tile {:?} has unknown source {}", pos, id)),
+                    }
+                }
+            }
+        }
+
+        if food_tiles != self.index.len() {
+            return Err(format!(
+                "food index holds {} tiles, grid has {}",
+                self.index.len(),
+                food_tiles
+            ));
+        }
+
+        for (source, total) in self.sources.iter().zip(source_totals.iter()) {
+            if source.amount != *total {
+                return Err(format!(
+                    "source {} has amount {}, its tiles hold {}",
+                    source.id, source.amount, total
+                ));
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (Grid, FoodSources) {
+        return (Grid::new((20, 20)), FoodSources::new());
+    }
+
+    #[test]
+    fn adding_the_same_tile_twice_indexes_it_once() {
+        let (mut grid, mut sources) = setup();
+
+        sources.add(&mut grid, (5, 5), 0, vec![(5, 5)], 10, 0.0);
+        sources.add(&mut grid, (5, 5), 0, vec![(5, 5)], 20, 0.0);
+
+        assert_eq!(sources.index().len(), 1);
+        assert_eq!(grid.tile((5, 5)).food.concentration, 20);
+        assert_eq!(sources.get(0).unwrap().amount, 0);
+        assert_eq!(sources.check_invariants(&grid), Ok(()));
+    }
+
+    #[test]
+    fn exhausted_tiles_leave_the_index() {
+        let (mut grid, mut sources) = setup();
+
+        sources.add(&mut grid, (2, 2), 0, vec![(2, 2)], 3, 0.0);
+        sources.add(&mut grid, (9, 1), 0, vec![(9, 1)], 5, 0.0);
+
+        for _ in 0..3 {
+            assert!(sources.harvest(&mut grid, (2, 2)));
+            assert_eq!(sources.check_invariants(&grid), Ok(()));
+        }
+        assert!(!sources.harvest(&mut grid, (2, 2)));
+
+        assert!(!sources.index().contains((2, 2)));
+        assert_eq!(sources.index().nearest((2, 2)), Some((9, 1)));
+        assert_eq!(
+            sources.drain_events(),
+            vec![FoodEvent::Depleted { source: 0, tick: 0 }]
+        );
+    }
+
+    #[test]
+    fn removing_tiles_updates_source_and_index() {
+        let (mut grid, mut sources) = setup();
+        let tiles = grid.tiles_in_radius((10, 10), 2);
+        let num_tiles = tiles.len() as u64;
+
+        sources.add(&mut grid, (10, 10), 2, tiles, num_tiles * 4, 0.0);
+        sources.remove_tile(&mut grid, (10, 10));
+        sources.remove_tile(&mut grid, (10, 11));
+
+        assert_eq!(sources.get(0).unwrap().amount, (num_tiles - 2) * 4);
+        assert_eq!(sources.index().len() as u64, num_tiles - 2);
+        assert_eq!(sources.check_invariants(&grid), Ok(()));
+    }
+
+    #[test]
+    fn removed_sources_dont_regrow() {
+        let (mut grid, mut sources) = setup();
+        let tiles = grid.tiles_in_radius((4, 4), 1);
+
+        sources.add(&mut grid, (4, 4), 1, tiles, 10, 2.0);
+        sources.harvest(&mut grid, (4, 4));
+        sources.remove_source(&mut grid, 0);
+
+        for _ in 0..20 {
+            sources.regrow(&mut grid);
+        }
+        assert!(sources.index().is_empty());
+        assert!(sources.get(0).unwrap().tiles().is_empty());
+        assert_eq!(sources.check_invariants(&grid), Ok(()));
+    }
+
+    #[test]
+    fn regrowth_refills_and_reindexes() {
+        let (mut grid, mut sources) = setup();
+
+        sources.add(&mut grid, (4, 4), 0, vec![(4, 4)], 2, 0.5);
+        sources.set_tick(7);
+        sources.harvest(&mut grid, (4, 4));
+        sources.harvest(&mut grid, (4, 4));
+        assert!(sources.index().is_empty());
+
+        sources.regrow(&mut grid);
+        assert!(sources.index().is_empty());
+        sources.regrow(&mut grid);
+        assert!(sources.index().contains((4, 4)));
+
+        for _ in 0..10 {
+            sources.regrow(&mut grid);
+            assert_eq!(sources.check_invariants(&grid), Ok(()));
+        }
+        assert_eq!(sources.get(0).unwrap().amount, 2);
+        assert_eq!(
+            sources.drain_events(),
+            vec![
+                FoodEvent::Depleted { source: 0, tick: 7 },
+                FoodEvent::Replenished { source: 0, tick: 7 },
+            ]
+        );
+    }
 }
@@ -0,0 +1,269 @@
+use std::f64::consts::PI;
+
+use rand::{distributions::Uniform, prelude::Distribution, Rng};
+
+use crate::{config::Config, map_file::MapFile, util::sample_normal};
+
+// Points drawn per cluster, overlapping draws land on the same tile
+const CLUSTER_SAMPLES: u32 = 48;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FoodLayout {
+    /// Piles scattered evenly over the grid
+    Uniform,
+    /// Gaussian blobs of food around random centers
+    Clusters,
+    /// Piles at a fixed distance around the nest
+    Ring,
+    /// Food lines of the map file
+    File(MapFile),
+}
+
+/// Food placed at world creation. Piles without explicit tiles cover the tiles within radius.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoodPile {
+    pub pos: (u32, u32),
+    pub radius: u32,
+    pub amount: u64,
+    pub tiles: Option<Vec<(u32, u32)>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FoodGenerator {
+    pub layout: FoodLayout,
+    /// Inclusive range of piles to generate
+    pub pile_count: (u32, u32),
+    /// Inclusive range of food per pile
+    pub pile_amount: (u32, u32),
+    pub pile_radius: u32,
+    /// Standard deviation of a cluster in tiles
+    pub cluster_spread: f64,
+    /// Distance of the ring from the nest in tiles
+    pub ring_distance: f64,
+    pub regrowth_rate: f64,
+}
+
+impl FoodGenerator {
+    pub fn from_config(config: &Config) -> Self {
+        let layout = match config.get_words("food_layout")[0].as_str() {
+            "uniform" => FoodLayout::Uniform,
+            "clusters" => FoodLayout::Clusters,
+            "ring" => FoodLayout::Ring,
+            "file" => FoodLayout::File(MapFile::from_config(config).unwrap_or_default()),
+            other => {
+                log::error!("unknown food layout '{}', using uniform", other);
+                FoodLayout::Uniform
+            }
+        };
+
+        return Self {
+            layout: layout,
+            pile_count: (
+                config.get_parameter("food_pile_count").vals[0] as u32,
+                config.get_parameter("food_pile_count").vals[1] as u32,
+            ),
+            pile_amount: (
+                config.get_parameter("food_pile_amount").vals[0] as u32,
+                config.get_parameter("food_pile_amount").vals[1] as u32,
+            ),
+            pile_radius: config.get_parameter("food_pile_radius").vals[0] as u32,
+            cluster_spread: config.get_parameter("food_cluster_spread").vals[0],
+            ring_distance: config.get_parameter("food_ring_distance").vals[0],
+            regrowth_rate: config.get_parameter("food_regrowth_rate").vals[0],
+        };
+    }
+
+    pub fn generate<R: Rng>(
+        &self,
+        rng: &mut R,
+        grid_size: (u32, u32),
+        nest: (u32, u32),
+    ) -> Vec<FoodPile> {
+        if let FoodLayout::File(map) = &self.layout {
+            return map
+                .food
+                .iter()
+                .filter(|pile| {
+                    let inside = pile.pos.0 < grid_size.0 && pile.pos.1 < grid_size.1;
+                    if !inside {
+                        log::error!("food pile at {:?} is outside the grid", pile.pos);
+                    }
+                    inside
+                })
+                .cloned()
+                .collect();
+        }
+
+        let range_count =
+            Uniform::from(self.pile_count.0..=self.pile_count.1.max(self.pile_count.0));
+        let range_amount =
+            Uniform::from(self.pile_amount.0..=self.pile_amount.1.max(self.pile_amount.0));
+        let range_x = Uniform::from(0..grid_size.0);
+        let range_y = Uniform::from(0..grid_size.1);
+
+        let num_piles = range_count.sample(rng);
+        let mut piles: Vec<FoodPile> = Vec::new();
+
+        // Random starting angle, so rings don't always line up the same way
+        let ring_phase = rng.gen_range(0.0..2.0 * PI);
+
+        for pile_index in 0..num_piles {
+            let amount = range_amount.sample(rng) as u64;
+
+            match self.layout {
+                FoodLayout::Uniform => {
+                    piles.push(FoodPile {
+                        pos: (range_x.sample(rng), range_y.sample(rng)),
+                        radius: self.pile_radius,
+                        amount: amount,
+                        tiles: None,
+                    });
+                }
+                FoodLayout::Clusters => {
+                    let center = (range_x.sample(rng), range_y.sample(rng));
+                    let mut tiles: Vec<(u32, u32)> = Vec::new();
+
+                    for _ in 0..CLUSTER_SAMPLES {
+                        let x = sample_normal(rng, center.0 as f64, self.cluster_spread);
+                        let y = sample_normal(rng, center.1 as f64, self.cluster_spread);
+                        let tile = clamp_to_grid(x, y, grid_size);
+
+                        if !tiles.contains(&tile) {
+                            tiles.push(tile);
+                        }
+                    }
+
+                    piles.push(FoodPile {
+                        pos: center,
+                        radius: self.cluster_spread.ceil() as u32,
+                        amount: amount,
+                        tiles: Some(tiles),
+                    });
+                }
+                FoodLayout::Ring => {
+                    let angle = ring_phase + 2.0 * PI * pile_index as f64 / num_piles as f64;
+                    let x = nest.0 as f64 + angle.cos() * self.ring_distance;
+                    let y = nest.1 as f64 + angle.sin() * self.ring_distance;
+
+                    piles.push(FoodPile {
+                        pos: clamp_to_grid(x, y, grid_size),
+                        radius: self.pile_radius,
+                        amount: amount,
+                        tiles: None,
+                    });
+                }
+                FoodLayout::File(_) => {}
+            }
+        }
+
+        return piles;
+    }
+}
+
+fn clamp_to_grid(x: f64, y: f64, grid_size: (u32, u32)) -> (u32, u32) {
+    return (
+        (x.round().max(0.0) as u32).min(grid_size.0 - 1),
+        (y.round().max(0.0) as u32).min(grid_size.1 - 1),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixture, tile::Terrain};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn generator(layout: FoodLayout) -> FoodGenerator {
+        return FoodGenerator {
+            layout: layout,
+            pile_count: (4, 6),
+            pile_amount: (100, 200),
+            pile_radius: 2,
+            cluster_spread: 4.0,
+            ring_distance: 15.0,
+            regrowth_rate: 0.0,
+        };
+    }
+
+    #[test]
+    fn generated_piles_stay_in_the_grid() {
+        // Wider than high, the y range used to come from the width
+        let grid_size = (50, 20);
+
+        for layout in [FoodLayout::Uniform, FoodLayout::Clusters, FoodLayout::Ring] {
+            for seed in 0..20 {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let piles = generator(layout.clone()).generate(&mut rng, grid_size, (45, 18));
+
+                assert!(piles.len() >= 4 && piles.len() <= 6);
+                for pile in piles.iter() {
+                    assert!(pile.pos.0 < grid_size.0 && pile.pos.1 < grid_size.1);
+                    for tile in pile.tiles.iter().flatten() {
+                        assert!(tile.0 < grid_size.0 && tile.1 < grid_size.1);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn clusters_and_rings_place_the_configured_amount_off_walls() {
+        let mut world = fixture::world();
+        world.place_wall_line((0, 8), (39, 8));
+        world.place_wall_line((8, 0), (8, 39));
+
+        let mut rng = StdRng::seed_from_u64(7);
+        for layout in [FoodLayout::Clusters, FoodLayout::Ring] {
+            let piles = generator(layout.clone()).generate(&mut rng, world.grid_size(), (20, 20));
+
+            for pile in piles.iter() {
+                assert!(pile.amount >= 100 && pile.amount <= 200);
+                if layout == FoodLayout::Ring {
+                    let (dx, dy) = (pile.pos.0 as f64 - 20.0, pile.pos.1 as f64 - 20.0);
+                    assert!(((dx * dx + dy * dy).sqrt() - 15.0).abs() <= 1.0);
+                }
+
+                if let Some(id) = world.add_food_pile(pile, 0.0) {
+                    assert_eq!(world.food_source(id).unwrap().amount, pile.amount);
+                }
+            }
+        }
+
+        for x in 0..40 {
+            for y in 0..40 {
+                let tile = world.tile((x, y));
+                if tile.terrain == Terrain::Wall {
+                    assert_eq!(tile.food.concentration, 0);
+                    assert_eq!(tile.food.source, None);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn file_layout_loads_the_map_piles_inside_the_grid() {
+        let mut config = fixture::config();
+        config.set_parameter("map_file", "example.map");
+        let generator = generator(FoodLayout::File(MapFile::from_config(&config).unwrap()));
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let piles = generator.generate(&mut rng, (100, 100), (50, 50));
+        let entries: Vec<((u32, u32), u64, u32)> = piles
+            .iter()
+            .map(|pile| (pile.pos, pile.amount, pile.radius))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ((20, 20), 2500, 0),
+                ((80, 20), 2500, 0),
+                ((50, 85), 6000, 2)
+            ]
+        );
+
+        // Piles past the edge of a smaller grid are dropped
+        let piles = generator.generate(&mut rng, (60, 60), (30, 30));
+        assert_eq!(piles.len(), 1);
+        assert_eq!(piles[0].pos, (20, 20));
+    }
+}
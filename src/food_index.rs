@@ -0,0 +1,182 @@
+use std::collections::{BTreeSet, HashMap};
+
+const CELL_SIZE: u32 = 8;
+
+/// Spatial hash of the tiles that currently hold food, keyed by grid position.
+/// Cells keep their tiles sorted, so lookups are deterministic.
+#[derive(Debug, Default)]
+pub struct FoodIndex {
+    cells: HashMap<(u32, u32), BTreeSet<(u32, u32)>>,
+    len: usize,
+    // Largest cell coordinates ever used, bounds the nearest search
+    max_cell: (u32, u32),
+}
+
+impl FoodIndex {
+    fn cell_of(pos: (u32, u32)) -> (u32, u32) {
+        return (pos.0 / CELL_SIZE, pos.1 / CELL_SIZE);
+    }
+
+    /// Returns false if the position was already indexed
+    pub fn insert(&mut self, pos: (u32, u32)) -> bool {
+        let cell = FoodIndex::cell_of(pos);
+        self.max_cell = (self.max_cell.0.max(cell.0), self.max_cell.1.max(cell.1));
+
+        let inserted = self
+            .cells
+            .entry(cell)
+            .or_insert_with(BTreeSet::new)
+            .insert(pos);
+
+        if inserted {
+            self.len += 1;
+        }
+
+        return inserted;
+    }
+
+    /// Returns false if the position wasn't indexed
+    pub fn remove(&mut self, pos: (u32, u32)) -> bool {
+        let cell = FoodIndex::cell_of(pos);
+
+        let removed = match self.cells.get_mut(&cell) {
+            Some(tiles) => tiles.remove(&pos),
+            None => false,
+        };
+
+        if removed {
+            self.len -= 1;
+            if self.cells[&cell].is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+
+        return removed;
+    }
+
+    pub fn contains(&self, pos: (u32, u32)) -> bool {
+        return match self.cells.get(&FoodIndex::cell_of(pos)) {
+            Some(tiles) => tiles.contains(&pos),
+            None => false,
+        };
+    }
+
+    pub fn len(&self) -> usize {
+        return self.len;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len == 0;
+    }
+
+    /// Closest indexed tile to pos, ties go to the smallest position
+    pub fn nearest(&self, pos: (u32, u32)) -> Option<(u32, u32)> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let center = FoodIndex::cell_of(pos);
+        let max_ring = center
+            .0
+            .max(center.1)
+            .max(self.max_cell.0.saturating_sub(center.0))
+            .max(self.max_cell.1.saturating_sub(center.1)) as i64;
+
+        let mut best: Option<((u32, u32), u64)> = None;
+
+        // Search rings of cells outwards, anything beyond ring k is more than k * CELL_SIZE away
+        for ring in 0..=max_ring {
+            for cell_x in center.0 as i64 - ring..=center.0 as i64 + ring {
+                for cell_y in center.1 as i64 - ring..=center.1 as i64 + ring {
+                    let on_ring = (cell_x - center.0 as i64).abs() == ring
+                        || (cell_y - center.1 as i64).abs() == ring;
+                    if !on_ring || cell_x < 0 || cell_y < 0 {
+                        continue;
+                    }
+
+                    if let Some(tiles) = self.cells.get(&(cell_x as u32, cell_y as u32)) {
+                        for tile in tiles.iter() {
+                            let dx = tile.0 as i64 - pos.0 as i64;
+                            let dy = tile.1 as i64 - pos.1 as i64;
+                            let dist_sq = (dx * dx + dy * dy) as u64;
+
+                            let closer = match best {
+                                Some((best_tile, best_dist_sq)) => {
+                                    dist_sq < best_dist_sq
+                                        || (dist_sq == best_dist_sq && *tile < best_tile)
+                                }
+                                None => true,
+                            };
+                            if closer {
+                                best = Some((*tile, dist_sq));
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let Some((_, best_dist_sq)) = best {
+                let ring_dist = (ring * CELL_SIZE as i64) as u64;
+                if best_dist_sq <= ring_dist * ring_dist {
+                    break;
+                }
+            }
+        }
+
+        return best.map(|(tile, _)| tile);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_and_remove_keep_len() {
+        let mut index = FoodIndex::default();
+
+        assert!(index.insert((3, 4)));
+        assert!(!index.insert((3, 4)));
+        assert!(index.insert((40, 4)));
+        assert_eq!(index.len(), 2);
+
+        assert!(index.remove((3, 4)));
+        assert!(!index.remove((3, 4)));
+        assert!(!index.contains((3, 4)));
+        assert_eq!(index.len(), 1);
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let mut index = FoodIndex::default();
+        let tiles = [(0, 0), (17, 3), (18, 30), (55, 60), (9, 9), (33, 2)];
+        for tile in tiles.iter() {
+            index.insert(*tile);
+        }
+
+        for x in (0..70).step_by(7) {
+            for y in (0..70).step_by(5) {
+                let expected = tiles
+                    .iter()
+                    .min_by_key(|t| {
+                        let dx = t.0 as i64 - x as i64;
+                        let dy = t.1 as i64 - y as i64;
+                        (dx * dx + dy * dy, **t)
+                    })
+                    .copied();
+
+                assert_eq!(index.nearest((x, y)), expected, "from {:?}", (x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_of_empty_index_is_none() {
+        let mut index = FoodIndex::default();
+        assert_eq!(index.nearest((5, 5)), None);
+
+        index.insert((1, 1));
+        index.remove((1, 1));
+        assert_eq!(index.nearest((5, 5)), None);
+    }
+}
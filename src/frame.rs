@@ -0,0 +1,272 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use sdl2::{
+    pixels::Color,
+    rect::{Point, Rect},
+};
+
+use crate::renderer::{Heatmap, Renderer};
+
+const BYTES_PER_PIXEL: usize = 4;
+
+/// Offscreen RGBA image, the renderer backend for exports without a window
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(size: (u32, u32), background: Color) -> Self {
+        let mut frame = Self {
+            width: size.0,
+            height: size.1,
+            pixels: vec![0; size.0 as usize * size.1 as usize * BYTES_PER_PIXEL],
+        };
+        frame.clear(background);
+
+        return frame;
+    }
+
+    /// Wraps pixels read back from a window, laid out as R, G, B, A rows without padding
+    pub fn from_pixels(size: (u32, u32), pixels: Vec<u8>) -> Self {
+        return Self {
+            width: size.0,
+            height: size.1,
+            pixels: pixels,
+        };
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        return (self.width, self.height);
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        return &self.pixels;
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        let offset = (y as usize * self.width as usize + x as usize) * BYTES_PER_PIXEL;
+        return Color::RGBA(
+            self.pixels[offset],
+            self.pixels[offset + 1],
+            self.pixels[offset + 2],
+            self.pixels[offset + 3],
+        );
+    }
+
+    /// Blends the color over the rectangle, parts outside the frame are cut off
+    fn blend_rect(&mut self, rect: Rect, color: Color) {
+        let left = rect.left().clamp(0, self.width as i32) as usize;
+        let right = rect.right().clamp(0, self.width as i32) as usize;
+        let top = rect.top().clamp(0, self.height as i32) as usize;
+        let bottom = rect.bottom().clamp(0, self.height as i32) as usize;
+
+        let alpha = color.a as u32;
+        let blend = |dst: u8, src: u8| -> u8 {
+            return ((src as u32 * alpha + dst as u32 * (255 - alpha)) / 255) as u8;
+        };
+
+        for y in top..bottom {
+            for x in left..right {
+                let offset = (y * self.width as usize + x) * BYTES_PER_PIXEL;
+                let pixel = &mut self.pixels[offset..offset + BYTES_PER_PIXEL];
+
+                pixel[0] = blend(pixel[0], color.r);
+                pixel[1] = blend(pixel[1], color.g);
+                pixel[2] = blend(pixel[2], color.b);
+                pixel[3] = pixel[3].max(color.a);
+            }
+        }
+    }
+
+    pub fn save_png(&self, path: &str) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        return self.write_png(BufWriter::new(file));
+    }
+
+    /// PNG file contents, for sending frames over the network
+    pub fn encode_png(&self) -> Result<Vec<u8>, String> {
+        let mut bytes: Vec<u8> = Vec::new();
+        self.write_png(&mut bytes)?;
+        return Ok(bytes);
+    }
+
+    fn write_png<W: Write>(&self, out: W) -> Result<(), String> {
+        let mut encoder = png::Encoder::new(out, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+        writer
+            .write_image_data(&self.pixels)
+            .map_err(|e| e.to_string())?;
+
+        return writer.finish().map_err(|e| e.to_string());
+    }
+}
+
+impl Renderer for Frame {
+    fn output_size(&self) -> (u32, u32) {
+        return self.size();
+    }
+
+    fn clear(&mut self, color: Color) {
+        for pixel in self.pixels.chunks_exact_mut(BYTES_PER_PIXEL) {
+            pixel.copy_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+    }
+
+    fn fill_rects(&mut self, rects: &[Rect], color: Color) {
+        for rect in rects {
+            self.blend_rect(*rect, color);
+        }
+    }
+
+    fn draw_rects(&mut self, rects: &[Rect], color: Color) {
+        for rect in rects {
+            let (x, y, w, h) = (rect.x(), rect.y(), rect.width(), rect.height());
+            self.fill_rects(
+                &[
+                    Rect::new(x, y, w, 1),
+                    Rect::new(x, y + h as i32 - 1, w, 1),
+                    Rect::new(x, y, 1, h),
+                    Rect::new(x + w as i32 - 1, y, 1, h),
+                ],
+                color,
+            );
+        }
+    }
+
+    fn draw_lines(&mut self, lines: &[(Point, Point)], color: Color) {
+        for (from, to) in lines {
+            // One pixel per step along the longer axis
+            let steps = (to.x() - from.x())
+                .abs()
+                .max((to.y() - from.y()).abs())
+                .max(1);
+            for step in 0..=steps {
+                let t = step as f64 / steps as f64;
+                let x = from.x() as f64 + (to.x() - from.x()) as f64 * t;
+                let y = from.y() as f64 + (to.y() - from.y()) as f64 * t;
+                self.blend_rect(Rect::new(x.round() as i32, y.round() as i32, 1, 1), color);
+            }
+        }
+    }
+
+    /// Redraws every cell, a frame doesn't keep the previous picture
+    fn draw_heatmap(&mut self, heatmap: &Heatmap, dest: Rect) {
+        let size = heatmap.size();
+        let cell_w = dest.width() as f64 / size.0 as f64;
+        let cell_h = dest.height() as f64 / size.1 as f64;
+
+        for x in 0..size.0 {
+            for y in 0..size.1 {
+                let color = heatmap.color((x, y));
+                if color.a == 0 {
+                    continue;
+                }
+
+                // Both corners are rounded, so neighbouring cells never leave gaps
+                let left = dest.x() + (x as f64 * cell_w).round() as i32;
+                let top = dest.y() + (y as f64 * cell_h).round() as i32;
+                let right = dest.x() + ((x + 1) as f64 * cell_w).round() as i32;
+                let bottom = dest.y() + ((y + 1) as f64 * cell_h).round() as i32;
+                if right > left && bottom > top {
+                    self.blend_rect(
+                        Rect::new(left, top, (right - left) as u32, (bottom - top) as u32),
+                        color,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Animated PNG written frame by frame. The frame count has to be known up front,
+/// frames past it are ignored and missing ones repeat the last frame on finish.
+pub struct Animation {
+    writer: png::Writer<BufWriter<File>>,
+    size: (u32, u32),
+    frames_left: u32,
+    // Pixels of the last frame written, the padding on finish
+    last_pixels: Vec<u8>,
+}
+
+impl Animation {
+    pub fn create(
+        path: &str,
+        size: (u32, u32),
+        num_frames: u32,
+        frame_millis: u16,
+    ) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+
+        let mut encoder = png::Encoder::new(BufWriter::new(file), size.0, size.1);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .set_animated(num_frames, 0)
+            .map_err(|e| e.to_string())?;
+        encoder
+            .set_frame_delay(frame_millis, 1000)
+            .map_err(|e| e.to_string())?;
+
+        return Ok(Self {
+            writer: encoder.write_header().map_err(|e| e.to_string())?,
+            size: size,
+            frames_left: num_frames,
+            last_pixels: vec![0; size.0 as usize * size.1 as usize * BYTES_PER_PIXEL],
+        });
+    }
+
+    pub fn add_frame(&mut self, frame: &Frame) -> Result<(), String> {
+        if self.frames_left == 0 {
+            return Ok(());
+        }
+        if frame.size() != self.size {
+            return Err("frame size doesn't match the animation".to_string());
+        }
+
+        self.frames_left -= 1;
+        self.last_pixels.copy_from_slice(frame.pixels());
+        return self
+            .writer
+            .write_image_data(frame.pixels())
+            .map_err(|e| e.to_string());
+    }
+
+    /// A run that stopped early still gives a valid file, the last frame is held
+    pub fn finish(mut self) -> Result<(), String> {
+        while self.frames_left > 0 {
+            self.frames_left -= 1;
+            self.writer
+                .write_image_data(&self.last_pixels)
+                .map_err(|e| e.to_string())?;
+        }
+
+        return self.writer.finish().map_err(|e| e.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_rects_clips_and_blends() {
+        let mut frame = Frame::new((4, 3), Color::RGB(0, 0, 0));
+
+        frame.fill_rects(&[Rect::new(-2, -2, 4, 4)], Color::RGBA(255, 0, 0, 255));
+        frame.fill_rects(&[Rect::new(1, 1, 10, 10)], Color::RGBA(0, 0, 255, 128));
+
+        assert_eq!(frame.pixel(0, 0), Color::RGBA(255, 0, 0, 255));
+        assert_eq!(frame.pixel(3, 0), Color::RGBA(0, 0, 0, 255));
+        assert_eq!(frame.pixel(1, 1), Color::RGBA(127, 0, 128, 255));
+        assert_eq!(frame.pixel(3, 2), Color::RGBA(0, 0, 128, 255));
+    }
+}
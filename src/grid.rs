@@ -0,0 +1,134 @@
+use crate::{
+    food::Food,
+    marker::Marker,
+    tile::{Terrain, Tile},
+};
+
+/// Inclusive bounding box of tiles changed since the last render
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirtyRegion {
+    pub min: (u32, u32),
+    pub max: (u32, u32),
+}
+
+impl DirtyRegion {
+    pub fn width(&self) -> u32 {
+        return self.max.0 - self.min.0 + 1;
+    }
+
+    pub fn height(&self) -> u32 {
+        return self.max.1 - self.min.1 + 1;
+    }
+
+    fn include(&mut self, pos: (u32, u32)) {
+        self.min = (self.min.0.min(pos.0), self.min.1.min(pos.1));
+        self.max = (self.max.0.max(pos.0), self.max.1.max(pos.1));
+    }
+}
+
+pub struct Grid {
+    tiles: Vec<Vec<Tile>>,
+    size: (u32, u32),
+    dirty: Option<DirtyRegion>,
+}
+
+impl Grid {
+    pub fn new(size: (u32, u32)) -> Self {
+        let empty_marker = Marker::new(0);
+        let empty_food = Food::default();
+
+        let mut tiles: Vec<Vec<Tile>> = Vec::new();
+        for x in 0..size.0 {
+            tiles.push(Vec::new());
+            for _ in 0..size.1 {
+                tiles[x as usize].push(Tile {
+                    markers: (empty_marker, empty_marker),
+                    food: empty_food,
+                    terrain: Terrain::Ground,
+                });
+            }
+        }
+
+        let mut grid = Self {
+            tiles: tiles,
+            size: size,
+            dirty: None,
+        };
+
+        grid.mark_all_dirty();
+
+        return grid;
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        return self.size;
+    }
+
+    /// Tiles within radius tiles of center, clipped to the grid
+    pub fn tiles_in_radius(&self, center: (u32, u32), radius: u32) -> Vec<(u32, u32)> {
+        let mut positions: Vec<(u32, u32)> = Vec::new();
+        let r = radius as i32;
+
+        for dx in -r..=r {
+            for dy in -r..=r {
+                let x = center.0 as i32 + dx;
+                let y = center.1 as i32 + dy;
+
+                if dx * dx + dy * dy <= r * r
+                    && x >= 0
+                    && y >= 0
+                    && x < self.size.0 as i32
+                    && y < self.size.1 as i32
+                {
+                    positions.push((x as u32, y as u32));
+                }
+            }
+        }
+
+        return positions;
+    }
+
+    pub fn tile(&self, pos: (u32, u32)) -> &Tile {
+        return &self.tiles[pos.0 as usize][pos.1 as usize];
+    }
+
+    /// Marks the tile dirty, so only borrow mutably when actually writing
+    pub fn tile_mut(&mut self, pos: (u32, u32)) -> &mut Tile {
+        self.mark_dirty(pos);
+        return &mut self.tiles[pos.0 as usize][pos.1 as usize];
+    }
+
+    pub fn tiles(&self) -> &Vec<Vec<Tile>> {
+        return &self.tiles;
+    }
+
+    pub fn tiles_mut(&mut self) -> &mut Vec<Vec<Tile>> {
+        self.mark_all_dirty();
+        return &mut self.tiles;
+    }
+
+    pub fn mark_dirty(&mut self, pos: (u32, u32)) {
+        match &mut self.dirty {
+            Some(region) => region.include(pos),
+            None => {
+                self.dirty = Some(DirtyRegion { min: pos, max: pos });
+            }
+        }
+    }
+
+    pub fn mark_all_dirty(&mut self) {
+        if self.size.0 == 0 || self.size.1 == 0 {
+            return;
+        }
+
+        self.dirty = Some(DirtyRegion {
+            min: (0, 0),
+            max: (self.size.0 - 1, self.size.1 - 1),
+        });
+    }
+
+    /// Returns the region changed since the last call and resets it
+    pub fn take_dirty(&mut self) -> Option<DirtyRegion> {
+        return self.dirty.take();
+    }
+}
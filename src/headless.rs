@@ -0,0 +1,218 @@
+use std::time::Instant;
+
+use crate::{
+    args::{flag_value, parse_flag, parse_size},
+    config::Config,
+    export::{pheromone_image, ExportSettings, FrameExport},
+    metrics::MetricsRecorder,
+    replay::{Replay, ReplayEvent, ReplayPlayer},
+    scenario::Scenario,
+    server::{Server, ServerSettings},
+    world::World,
+};
+
+/// Runs the simulation without a window:
+/// `ants headless --ticks 5000 --metrics out.csv --interval 100`.
+/// `--frames dir` and `--apng out.png` capture the world every `--frame-every` ticks
+/// at `--frame-size WxH`, `--pheromones out.png` saves the trails at the end.
+/// With `--replay run.replay` the recorded config, seed and edits are used instead,
+/// pauses are skipped and the run stops where the recording did.
+/// `--scenario file` runs scripted edits, see Scenario.
+/// `--serve 127.0.0.1:9000` streams stats and `--serve-size WxH` frames every
+/// `--serve-every` ticks over WebSocket and takes commands from the clients.
+pub fn run(config: &Config, args: &[String]) {
+    let replay = match flag_value(args, "--replay") {
+        Some(path) => match Replay::load(path) {
+            Ok(replay) => Some(replay),
+            Err(e) => {
+                log::error!("replay error: {}", &e);
+                std::process::exit(-1);
+            }
+        },
+        None => None,
+    };
+    let config = match replay.as_ref() {
+        Some(replay) => replay.config(),
+        None => config.clone(),
+    };
+    let mut config = config;
+
+    let ticks: u64 = parse_flag(
+        args,
+        "--ticks",
+        replay.as_ref().map_or(10000, |replay| replay.end_tick),
+    );
+    let interval: u64 = parse_flag(
+        args,
+        "--interval",
+        config.get_parameter("metrics_interval").vals[0] as u64,
+    );
+
+    let mut world = World::from_config(&config);
+    let mut player = replay.as_ref().map(ReplayPlayer::new);
+    let mut scenario = Scenario::from_args(args);
+
+    let mut recorder = match flag_value(args, "--metrics") {
+        Some(path) => match MetricsRecorder::create(path, interval) {
+            Ok(recorder) => Some(recorder),
+            Err(e) => {
+                log::error!("metrics error: {}", &e);
+                std::process::exit(-1);
+            }
+        },
+        None => None,
+    };
+
+    let mut export =
+        if flag_value(args, "--frames").is_some() || flag_value(args, "--apng").is_some() {
+            let settings = ExportSettings {
+                frame_dir: flag_value(args, "--frames").map(|dir| dir.to_string()),
+                animation_path: flag_value(args, "--apng").map(|path| path.to_string()),
+                frame_every: parse_flag(args, "--frame-every", 100u64).max(1),
+                frame_size: parse_size(
+                    args,
+                    "--frame-size",
+                    (
+                        config.get_parameter("win_size").vals[0] as u32,
+                        config.get_parameter("win_size").vals[1] as u32,
+                    ),
+                ),
+                frame_millis: parse_flag(args, "--frame-millis", 100),
+            };
+
+            match FrameExport::create(settings, &config, &world, ticks) {
+                Ok(export) => Some(export),
+                Err(e) => {
+                    log::error!("export error: {}", &e);
+                    std::process::exit(-1);
+                }
+            }
+        } else {
+            None
+        };
+
+    let mut server = match flag_value(args, "--serve") {
+        Some(addr) => {
+            let settings = ServerSettings {
+                addr: addr.to_string(),
+                every: parse_flag(args, "--serve-every", 10u64).max(1),
+                frame_size: parse_size(args, "--serve-size", (200, 200)),
+            };
+
+            match Server::start(settings, &config, &world) {
+                Ok(server) => {
+                    // Printed as the log is off by default
+                    println!("serving on http://{}", server.local_addr());
+                    Some(server)
+                }
+                Err(e) => {
+                    log::error!("server error: {}", &e);
+                    std::process::exit(-1);
+                }
+            }
+        }
+        None => None,
+    };
+
+    let start = Instant::now();
+
+    if let Some(export) = export.as_mut() {
+        match export.capture(&world) {
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("export error: {}", &e);
+            }
+        }
+    }
+
+    for _ in 0..ticks {
+        if let Some(server) = server.as_mut() {
+            server.steer(&mut world, &mut config);
+        }
+
+        if let Some(player) = player.as_mut() {
+            for event in player.due(world.tick()) {
+                if let ReplayEvent::Command(command) = event {
+                    match world.apply(&command) {
+                        Ok(_) => {}
+                        Err(e) => {
+                            log::error!("replay error: {}", &e);
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(scenario) = scenario.as_mut() {
+            for command in scenario.due(&world) {
+                match world.apply(&command) {
+                    Ok(_) => {}
+                    Err(e) => {
+                        log::error!("scenario error: {}", &e);
+                    }
+                }
+            }
+        }
+
+        world.update();
+
+        if let Some(recorder) = recorder.as_mut() {
+            match recorder.record(&world) {
+                Ok(_) => {}
+                Err(e) => {
+                    log::error!("metrics error: {}", &e);
+                }
+            }
+        }
+
+        if let Some(export) = export.as_mut() {
+            match export.capture(&world) {
+                Ok(_) => {}
+                Err(e) => {
+                    log::error!("export error: {}", &e);
+                }
+            }
+        }
+
+        if let Some(server) = server.as_ref() {
+            server.publish(&world);
+        }
+    }
+
+    if let Some(export) = export {
+        match export.finish() {
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("export error: {}", &e);
+            }
+        }
+    }
+
+    if let Some(path) = flag_value(args, "--pheromones") {
+        let frame = pheromone_image(&world, config.get_parameter("layer_marker_max").vals[0]);
+        match frame.save_png(path) {
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("export error: {}", &e);
+            }
+        }
+    }
+
+    if let Some(recorder) = recorder.as_mut() {
+        match recorder.flush() {
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("metrics error: {}", &e);
+            }
+        }
+    }
+
+    let stats = world.stats();
+    println!(
+        "{} ticks in {:.2}s, food collected {}, food remaining {}",
+        stats.tick,
+        start.elapsed().as_secs_f64(),
+        stats.food_collected,
+        stats.food_remaining
+    );
+}
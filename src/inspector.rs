@@ -0,0 +1,163 @@
+use std::collections::VecDeque;
+
+use glam::DVec2;
+use sdl2::{
+    pixels::Color,
+    rect::{Point, Rect},
+};
+
+use crate::{
+    ant::state_name, draw::ant_rect, renderer::Renderer, viewport::Viewport, world::World,
+};
+
+// Number of ticks of path kept for the selected ant
+const PATH_LENGTH: usize = 300;
+// Clicks within this many world units of an ant select it
+const SELECT_DISTANCE: f64 = 10.0;
+
+const HIGHLIGHT_COLOR: Color = Color::RGBA(255, 0, 200, 255);
+const PATH_COLOR: Color = Color::RGBA(255, 0, 200, 140);
+
+/// Hovered tile and selected ant, shown in a panel and highlighted in the world
+pub struct Inspector {
+    hovered_tile: Option<(u32, u32)>,
+    selected_ant: Option<u64>,
+    path: VecDeque<DVec2>,
+}
+
+impl Inspector {
+    pub fn new() -> Self {
+        return Self {
+            hovered_tile: None,
+            selected_ant: None,
+            path: VecDeque::new(),
+        };
+    }
+
+    pub fn hover(&mut self, world: &World, world_pos: Option<DVec2>) {
+        self.hovered_tile = world_pos.map(|pos| world.world_to_grid(pos));
+    }
+
+    /// Selects the ant closest to the position, returns false if none is close enough
+    pub fn select_at(&mut self, world: &World, world_pos: DVec2) -> bool {
+        match world.ant_near(world_pos, SELECT_DISTANCE) {
+            Some(ant) => {
+                self.selected_ant = Some(ant.id());
+                self.path.clear();
+                return true;
+            }
+            None => {
+                return false;
+            }
+        }
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selected_ant = None;
+        self.path.clear();
+    }
+
+    /// Call once per tick to extend the recent path of the selected ant
+    pub fn record(&mut self, world: &World) {
+        let ant_pos = match self.selected_ant.and_then(|id| world.ant(id)) {
+            Some(ant) => ant.pos(),
+            None => {
+                self.clear_selection();
+                return;
+            }
+        };
+
+        self.path.push_back(ant_pos);
+        while self.path.len() > PATH_LENGTH {
+            self.path.pop_front();
+        }
+    }
+
+    pub fn lines(&self, world: &World) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+
+        if let Some(grid_pos) = self.hovered_tile {
+            let tile = world.tile(grid_pos);
+
+            lines.push(format!("tile {},{}", grid_pos.0, grid_pos.1));
+            lines.push(format!(
+                "  to-home {:.2}  (type {})",
+                tile.markers.0.strength, tile.markers.0.m_type
+            ));
+            lines.push(format!(
+                "  to-food {:.2}  (type {})",
+                tile.markers.1.strength, tile.markers.1.m_type
+            ));
+            lines.push(format!("  food {}", tile.food.concentration));
+
+            if let Some(source) = tile.food.source.and_then(|id| world.food_source(id)) {
+                lines.push(format!(
+                    "source {} at {},{} r {}",
+                    source.id, source.pos.0, source.pos.1, source.radius
+                ));
+                lines.push(format!("  amount {}/{}", source.amount, source.capacity));
+                lines.push(format!("  harvested {}", source.harvested));
+                lines.push(format!("  regrowth {:.2}/tick", source.regrowth_rate));
+            }
+        }
+
+        if let Some(ant) = self.selected_ant.and_then(|id| world.ant(id)) {
+            lines.push(format!("ant {}", ant.id()));
+            lines.push(format!(
+                "  state {} ({})",
+                ant.state(),
+                state_name(ant.state())
+            ));
+            lines.push(format!("  caste {}", world.caste_name(ant.caste())));
+            lines.push(format!("  pos {:.1},{:.1}", ant.pos().x, ant.pos().y));
+            lines.push(format!("  speed {:.2}", ant.speed()));
+            lines.push(format!("  perception radius {}", ant.perception_radius()));
+            lines.push(format!("  pheromone radius {}", ant.pheromone_radius()));
+            lines.push(format!(
+                "  target tile {},{}",
+                ant.current_target_tile().0,
+                ant.current_target_tile().1
+            ));
+            lines.push(format!("  carrying {}", ant.carrying()));
+            lines.push(format!("  energy {:.0}", ant.energy()));
+        }
+
+        return lines;
+    }
+
+    pub fn render<R: Renderer>(&self, renderer: &mut R, viewport: &Viewport, world: &World) {
+        let ant = match self.selected_ant.and_then(|id| world.ant(id)) {
+            Some(ant) => ant,
+            None => return,
+        };
+
+        // Path, broken up wherever the ant wrapped around the world edge
+        let max_step = ant.speed() * 4.0 + 1.0;
+        let points: Vec<DVec2> = self.path.iter().copied().collect();
+        let mut lines: Vec<(Point, Point)> = Vec::new();
+
+        for segment in points.windows(2) {
+            if segment[0].distance(segment[1]) > max_step {
+                continue;
+            }
+
+            let from = viewport.world_to_screen(segment[0]);
+            let to = viewport.world_to_screen(segment[1]);
+            lines.push((
+                Point::new(from.x as i32, from.y as i32),
+                Point::new(to.x as i32, to.y as i32),
+            ));
+        }
+        renderer.draw_lines(&lines, PATH_COLOR);
+
+        // Outline around the ant
+        let target = ant_rect(ant, viewport);
+        let outline = Rect::new(
+            target.x() - 4,
+            target.y() - 4,
+            target.width() + 8,
+            target.height() + 8,
+        );
+        renderer.draw_rects(&[outline], HIGHLIGHT_COLOR);
+    }
+}
@@ -0,0 +1,249 @@
+use sdl2::pixels::Color;
+
+use crate::tile::{Terrain, Tile};
+
+pub const WALL_COLOR: Color = Color::RGBA(60, 60, 60, 255);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderLayer {
+    Combined,
+    ToFood,
+    ToHome,
+    Food,
+    Terrain,
+}
+
+impl RenderLayer {
+    pub const ALL: [RenderLayer; 5] = [
+        RenderLayer::Combined,
+        RenderLayer::ToFood,
+        RenderLayer::ToHome,
+        RenderLayer::Food,
+        RenderLayer::Terrain,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RenderLayer::Combined => "combined",
+            RenderLayer::ToFood => "to-food",
+            RenderLayer::ToHome => "to-home",
+            RenderLayer::Food => "food",
+            RenderLayer::Terrain => "terrain",
+        }
+    }
+
+    fn index(&self) -> usize {
+        return RenderLayer::ALL.iter().position(|l| l == self).unwrap();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorMap {
+    Heat,
+    Viridis,
+    Grayscale,
+}
+
+impl ColorMap {
+    pub fn next(&self) -> Self {
+        match self {
+            ColorMap::Heat => ColorMap::Viridis,
+            ColorMap::Viridis => ColorMap::Grayscale,
+            ColorMap::Grayscale => ColorMap::Heat,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorMap::Heat => "heat",
+            ColorMap::Viridis => "viridis",
+            ColorMap::Grayscale => "grayscale",
+        }
+    }
+
+    /// Maps t in 0..=1 onto the color map by interpolating between its stops
+    pub fn sample(&self, t: f64) -> Color {
+        let stops: &[(u8, u8, u8)] = match self {
+            ColorMap::Heat => &[
+                (0, 0, 0),
+                (180, 0, 0),
+                (255, 120, 0),
+                (255, 230, 60),
+                (255, 255, 255),
+            ],
+            ColorMap::Viridis => &[
+                (68, 1, 84),
+                (59, 82, 139),
+                (33, 145, 140),
+                (94, 201, 98),
+                (253, 231, 37),
+            ],
+            ColorMap::Grayscale => &[(0, 0, 0), (255, 255, 255)],
+        };
+
+        let scaled = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+        let index = (scaled.floor() as usize).min(stops.len() - 2);
+        let frac = scaled - index as f64;
+
+        let (a, b) = (stops[index], stops[index + 1]);
+        let lerp = |from: u8, to: u8| (from as f64 + (to as f64 - from as f64) * frac) as u8;
+
+        return Color::RGB(lerp(a.0, b.0), lerp(a.1, b.1), lerp(a.2, b.2));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scaling {
+    Linear,
+    Log,
+}
+
+impl Scaling {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scaling::Linear => "linear",
+            Scaling::Log => "log",
+        }
+    }
+
+    /// Normalizes a value to 0..=1 relative to max
+    pub fn apply(&self, value: f64, max: f64) -> f64 {
+        if value <= 0.0 || max <= 0.0 {
+            return 0.0;
+        }
+
+        let t = match self {
+            Scaling::Linear => value / max,
+            Scaling::Log => (1.0 + value).ln() / (1.0 + max).ln(),
+        };
+
+        return t.clamp(0.0, 1.0);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LayerStyle {
+    pub color_map: ColorMap,
+    pub scaling: Scaling,
+}
+
+/// Which layer is drawn and how each layer maps tile values to colors
+#[derive(Debug, Clone)]
+pub struct LayerSettings {
+    active: RenderLayer,
+    styles: [LayerStyle; 5],
+
+    marker_max: f64,
+    food_max: f64,
+}
+
+impl LayerSettings {
+    pub fn new(marker_max: f64, food_max: f64) -> Self {
+        let heat = LayerStyle {
+            color_map: ColorMap::Heat,
+            scaling: Scaling::Log,
+        };
+
+        return Self {
+            active: RenderLayer::Combined,
+            styles: [
+                heat,
+                heat,
+                heat,
+                LayerStyle {
+                    color_map: ColorMap::Viridis,
+                    scaling: Scaling::Linear,
+                },
+                heat,
+            ],
+
+            marker_max: marker_max,
+            food_max: food_max,
+        };
+    }
+
+    /// Values drawn at full color, the chosen layer and styles are kept
+    pub fn set_limits(&mut self, marker_max: f64, food_max: f64) {
+        self.marker_max = marker_max;
+        self.food_max = food_max;
+    }
+
+    pub fn active(&self) -> RenderLayer {
+        return self.active;
+    }
+
+    pub fn active_style(&self) -> LayerStyle {
+        return self.styles[self.active.index()];
+    }
+
+    pub fn set_active(&mut self, layer: RenderLayer) {
+        self.active = layer;
+    }
+
+    pub fn cycle_color_map(&mut self) {
+        let style = &mut self.styles[self.active.index()];
+        style.color_map = style.color_map.next();
+    }
+
+    pub fn toggle_scaling(&mut self) {
+        let style = &mut self.styles[self.active.index()];
+        style.scaling = match style.scaling {
+            Scaling::Linear => Scaling::Log,
+            Scaling::Log => Scaling::Linear,
+        };
+    }
+
+    pub fn tile_color(&self, tile: &Tile) -> Color {
+        let style = self.active_style();
+
+        if tile.terrain == Terrain::Wall {
+            return WALL_COLOR;
+        }
+
+        match self.active {
+            RenderLayer::ToFood => {
+                return style.color_map.sample(
+                    style
+                        .scaling
+                        .apply(tile.to_food_strength(), self.marker_max),
+                );
+            }
+            RenderLayer::ToHome => {
+                return style.color_map.sample(
+                    style
+                        .scaling
+                        .apply(tile.to_home_strength(), self.marker_max),
+                );
+            }
+            RenderLayer::Food => {
+                return style.color_map.sample(
+                    style
+                        .scaling
+                        .apply(tile.food.concentration as f64, self.food_max),
+                );
+            }
+            RenderLayer::Terrain => {
+                return Color::RGBA(0, 0, 0, 0);
+            }
+            RenderLayer::Combined => {
+                // Red: to-home, green: food, blue: to-food, alpha follows the strongest channel
+                let home = style
+                    .scaling
+                    .apply(tile.to_home_strength(), self.marker_max);
+                let food = style
+                    .scaling
+                    .apply(tile.food.concentration as f64, self.food_max);
+                let to_food = style
+                    .scaling
+                    .apply(tile.to_food_strength(), self.marker_max);
+
+                return Color::RGBA(
+                    (home * 255.0) as u8,
+                    (food * 255.0) as u8,
+                    (to_food * 255.0) as u8,
+                    (home.max(food).max(to_food) * 255.0) as u8,
+                );
+            }
+        }
+    }
+}
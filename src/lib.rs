@@ -0,0 +1,44 @@
+//! The simulation and its front-ends, used by the `ants` binary and the Python module
+
+pub mod ant;
+pub mod ant_hill;
+pub mod app;
+pub mod args;
+pub mod colony;
+pub mod command;
+pub mod config;
+pub mod draw;
+pub mod export;
+#[cfg(test)]
+mod fixture;
+pub mod font;
+pub mod food;
+pub mod food_gen;
+pub mod food_index;
+pub mod frame;
+pub mod grid;
+pub mod headless;
+pub mod inspector;
+pub mod layers;
+pub mod map_file;
+pub mod marker;
+pub mod metrics;
+pub mod overlay;
+pub mod pacing;
+#[cfg(feature = "python")]
+mod python;
+pub mod reload;
+pub mod renderer;
+pub mod replay;
+pub mod scenario;
+pub mod server;
+pub mod stats;
+pub mod sweep;
+pub mod tile;
+pub mod tile_texture;
+pub mod tools;
+pub mod traits;
+pub mod tui;
+pub mod util;
+pub mod viewport;
+pub mod world;
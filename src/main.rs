@@ -1,18 +1,23 @@
-use crate::app::*;
-
-mod ant;
-mod ant_hill;
-mod app;
-mod colony;
-mod config;
-mod food;
-mod marker;
-mod tile;
-mod util;
-mod world;
+use ants::{app::*, args, headless, replay, scenario, sweep, tui};
 
 // Yeah sorry not gonna add documentation
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
     let app = App::init();
-    app.run();
+
+    match args.first().map(|arg| arg.as_str()) {
+        Some("headless") => headless::run(app.config(), &args[1..]),
+        Some("sweep") => sweep::run(app.config(), &args[1..]),
+        Some("tui") => tui::run(app.config(), scenario::Scenario::from_args(&args[1..])),
+        Some("replay") => match args.get(1).map(|path| replay::Replay::load(path)) {
+            Some(Ok(replay)) => app.run(Some(replay), None, None),
+            Some(Err(e)) => log::error!("replay error: {}", &e),
+            None => log::error!("usage: ants replay <file>"),
+        },
+        _ => app.run(
+            None,
+            args::flag_value(&args, "--record"),
+            scenario::Scenario::from_args(&args),
+        ),
+    }
 }
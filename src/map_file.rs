@@ -0,0 +1,117 @@
+use crate::{config::Config, food_gen::FoodPile};
+
+/// Nest placement from a map file, radius in world units
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NestEntry {
+    pub pos: (u32, u32),
+    pub radius: Option<f64>,
+}
+
+/// Hand made layout, one entry per line:
+/// `food x y amount [radius]` and `nest x y [radius]`, '#' starts a comment.
+/// Positions are grid coordinates.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MapFile {
+    pub food: Vec<FoodPile>,
+    pub nest: Option<NestEntry>,
+}
+
+impl MapFile {
+    /// The file named by "map_file", None if it is "none"
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let path = config.get_words("map_file")[0].clone();
+        if path == "none" {
+            return None;
+        }
+
+        let result = config
+            .read_file(&path)
+            .and_then(|content| MapFile::parse(&content));
+
+        return match result {
+            Ok(map) => Some(map),
+            Err(e) => {
+                log::error!("failed to read map file '{}': {}", path, e);
+                Some(MapFile::default())
+            }
+        };
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut map = MapFile::default();
+
+        for (line_num, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let numbers = words[1..]
+                .iter()
+                .map(|w| w.parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|_| format!("line {}: invalid number", line_num + 1))?;
+
+            match (words[0], numbers.len()) {
+                ("food", 3) | ("food", 4) => {
+                    map.food.push(FoodPile {
+                        pos: (numbers[0] as u32, numbers[1] as u32),
+                        radius: numbers.get(3).copied().unwrap_or(0.0) as u32,
+                        amount: numbers[2] as u64,
+                        tiles: None,
+                    });
+                }
+                ("nest", 2) | ("nest", 3) => {
+                    map.nest = Some(NestEntry {
+                        pos: (numbers[0] as u32, numbers[1] as u32),
+                        radius: numbers.get(2).copied(),
+                    });
+                }
+                _ => {
+                    return Err(format!(
+                        "line {}: expected 'food x y amount [radius]' or 'nest x y [radius]'",
+                        line_num + 1
+                    ));
+                }
+            }
+        }
+
+        return Ok(map);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_parse_and_errors_name_the_line() {
+        let map = MapFile::parse(
+            "# two piles\nfood 3 4 200\nfood 10 12 500 2 # with radius\n\nnest 20 21 15.5\n",
+        )
+        .unwrap();
+
+        assert_eq!(map.food.len(), 2);
+        assert_eq!(map.food[0].pos, (3, 4));
+        assert_eq!(map.food[0].amount, 200);
+        assert_eq!(map.food[0].radius, 0);
+        assert_eq!(map.food[1].radius, 2);
+        assert_eq!(
+            map.nest,
+            Some(NestEntry {
+                pos: (20, 21),
+                radius: Some(15.5),
+            })
+        );
+
+        assert_eq!(
+            MapFile::parse("nest 1 1\nfood 1 x 5\n"),
+            Err("line 2: invalid number".to_string())
+        );
+        assert!(MapFile::parse("food 1 2\n")
+            .unwrap_err()
+            .starts_with("line 1: expected"));
+        assert!(MapFile::parse("wall 1 2\n").is_err());
+    }
+}
@@ -15,9 +15,40 @@ impl Marker {
             strength: DEFAULT_STRENGTH,
         };
     }
+    /// Weakens the marker by amount, down to nothing
+    pub fn fade(&mut self, amount: f64) {
+        if self.m_type != 0 {
+            self.strength = (self.strength - amount).max(0.0);
+        }
+    }
     pub fn update(&mut self) {
         if self.m_type != 0 {
             self.strength = (self.strength - DEGRADATION_RATE).clamp(0.0, DEFAULT_STRENGTH);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_clamps_strength() {
+        let mut strong = Marker::new(1);
+        strong.strength = DEFAULT_STRENGTH * 3.0;
+        strong.update();
+        assert_eq!(strong.strength, DEFAULT_STRENGTH);
+
+        let mut faded = Marker::new(2);
+        faded.strength = DEGRADATION_RATE / 2.0;
+        faded.update();
+        faded.update();
+        assert_eq!(faded.strength, 0.0);
+
+        // Empty markers are left alone
+        let mut empty = Marker::new(0);
+        empty.strength = -1.0;
+        empty.update();
+        assert_eq!(empty.strength, -1.0);
+    }
+}
@@ -0,0 +1,277 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use serde::Serialize;
+
+use crate::{
+    ant::{state_name, NUM_STATES},
+    stats::WorldStats,
+    world::World,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetricsFormat {
+    Csv,
+    JsonLines,
+}
+
+impl MetricsFormat {
+    /// .jsonl and .json files get JSON Lines, anything else CSV
+    pub fn from_path(path: &str) -> Self {
+        if path.ends_with(".jsonl") || path.ends_with(".json") {
+            return MetricsFormat::JsonLines;
+        }
+        return MetricsFormat::Csv;
+    }
+}
+
+/// JSON Lines row: the stats as the server sends them, plus the trips of the last interval
+/// and the run they belong to
+#[derive(Serialize)]
+struct JsonRow<'a> {
+    #[serde(flatten)]
+    stats: &'a WorldStats,
+    trips: u64,
+    mean_trip_length: f64,
+    run: u64,
+}
+
+/// Writes a row of world statistics every interval ticks
+pub struct MetricsRecorder {
+    writer: BufWriter<File>,
+    format: MetricsFormat,
+    interval: u64,
+
+    // Trip totals at the previous sample, the mean trip length only covers the last interval
+    last_trips: u64,
+    last_trip_ticks: u64,
+    // Worlds recorded before the current one, the tick starts over with each
+    run: u64,
+}
+
+impl MetricsRecorder {
+    pub fn create(path: &str, interval: u64) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+
+        let mut recorder = Self {
+            writer: BufWriter::new(file),
+            format: MetricsFormat::from_path(path),
+            interval: interval.max(1),
+
+            last_trips: 0,
+            last_trip_ticks: 0,
+            run: 0,
+        };
+
+        if recorder.format == MetricsFormat::Csv {
+            let mut header = vec![
+                "tick".to_string(),
+                "population".to_string(),
+                "births".to_string(),
+                "deaths".to_string(),
+                "food_collected".to_string(),
+                "food_stored".to_string(),
+                "food_remaining".to_string(),
+                "food_carried".to_string(),
+            ];
+            for state in 0..NUM_STATES {
+                header.push(format!(
+                    "ants_{}",
+                    state_name(state as u8).replace(" ", "_")
+                ));
+            }
+            header.push("to_home_mass".to_string());
+            header.push("to_food_mass".to_string());
+            header.push("trips".to_string());
+            header.push("mean_trip_length".to_string());
+            header.push("run".to_string());
+
+            recorder.write_line(&header.join(","))?;
+        }
+
+        return Ok(recorder);
+    }
+
+    /// Call once per tick, only samples when the tick is a multiple of the interval
+    pub fn record(&mut self, world: &World) -> Result<(), String> {
+        if world.tick() % self.interval != 0 {
+            return Ok(());
+        }
+
+        return self.sample(&world.stats());
+    }
+
+    pub fn sample(&mut self, stats: &WorldStats) -> Result<(), String> {
+        let trips = stats.trips_completed - self.last_trips;
+        let trip_ticks = stats.trip_ticks_total - self.last_trip_ticks;
+        self.last_trips = stats.trips_completed;
+        self.last_trip_ticks = stats.trip_ticks_total;
+
+        let mean_trip_length = if trips > 0 {
+            trip_ticks as f64 / trips as f64
+        } else {
+            0.0
+        };
+
+        let line = match self.format {
+            MetricsFormat::Csv => {
+                let mut fields = vec![
+                    stats.tick.to_string(),
+                    stats.num_ants.to_string(),
+                    stats.births.to_string(),
+                    stats.deaths.to_string(),
+                    stats.food_collected.to_string(),
+                    stats.food_stored.to_string(),
+                    stats.food_remaining.to_string(),
+                    stats.food_carried.to_string(),
+                ];
+                for count in stats.ants_per_state.iter() {
+                    fields.push(count.to_string());
+                }
+                fields.push(format!("{:.2}", stats.to_home_mass));
+                fields.push(format!("{:.2}", stats.to_food_mass));
+                fields.push(trips.to_string());
+                fields.push(format!("{:.2}", mean_trip_length));
+                fields.push(self.run.to_string());
+
+                fields.join(",")
+            }
+            MetricsFormat::JsonLines => {
+                let row = JsonRow {
+                    stats: stats,
+                    trips: trips,
+                    mean_trip_length: mean_trip_length,
+                    run: self.run,
+                };
+                serde_json::to_string(&row).map_err(|e| e.to_string())?
+            }
+        };
+
+        return self.write_line(&line);
+    }
+
+    /// Call when the world is rebuilt, the following rows count as the next run
+    pub fn reset(&mut self) {
+        self.run += 1;
+        self.last_trips = 0;
+        self.last_trip_ticks = 0;
+    }
+
+    pub fn flush(&mut self) -> Result<(), String> {
+        return self.writer.flush().map_err(|e| e.to_string());
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), String> {
+        return writeln!(self.writer, "{}", line).map_err(|e| e.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    // Two samples of the fixture world, then two of a new one if reset is set
+    fn record(extension: &str, reset: bool) -> Vec<String> {
+        let path = std::env::temp_dir().join(format!(
+            "ants_metrics_{}_{}.{}",
+            std::process::id(),
+            reset,
+            extension
+        ));
+        let path = path.to_string_lossy().to_string();
+
+        let mut recorder = MetricsRecorder::create(&path, 5).unwrap();
+        for _ in 0..(reset as usize + 1) {
+            let mut world = fixture::world();
+            for _ in 0..10 {
+                recorder.record(&world).unwrap();
+                world.update();
+            }
+            recorder.reset();
+        }
+        recorder.flush().unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        return text.lines().map(|line| line.to_string()).collect();
+    }
+
+    #[test]
+    fn csv_has_a_header_and_a_row_per_sample() {
+        let lines = record("csv", false);
+
+        assert_eq!(lines.len(), 3);
+        let header: Vec<&str> = lines[0].split(',').collect();
+        assert_eq!(header[..3], ["tick", "population", "births"]);
+        assert!(header.contains(&"ants_target_food"));
+        assert_eq!(header[header.len() - 2..], ["mean_trip_length", "run"]);
+
+        for (line, tick) in lines[1..].iter().zip(["0", "5"]) {
+            let fields: Vec<&str> = line.split(',').collect();
+            assert_eq!(fields.len(), header.len());
+            assert_eq!(fields[0], tick);
+            assert_eq!(fields[1], "1");
+            assert_eq!(fields.last(), Some(&"0"));
+        }
+    }
+
+    #[test]
+    fn json_lines_hold_the_stats() {
+        let lines = record("jsonl", false);
+
+        assert_eq!(lines.len(), 2);
+        let row: serde_json::Value = serde_json::from_str(&lines[1]).unwrap();
+        assert_eq!(row["tick"], 5);
+        assert_eq!(row["num_ants"], 1);
+        assert_eq!(row["ants_per_state"].as_array().unwrap().len(), NUM_STATES);
+        assert_eq!(row["trips"], 0);
+        assert_eq!(row["mean_trip_length"], 0.0);
+        assert_eq!(row["run"], 0);
+    }
+
+    #[test]
+    fn rows_after_a_reset_start_the_next_run() {
+        let lines = record("csv", true);
+
+        let rows: Vec<(&str, &str)> = lines[1..]
+            .iter()
+            .map(|line| {
+                let fields: Vec<&str> = line.split(',').collect();
+                return (fields[0], *fields.last().unwrap());
+            })
+            .collect();
+        assert_eq!(rows, vec![("0", "0"), ("5", "0"), ("0", "1"), ("5", "1")]);
+    }
+
+    #[test]
+    fn trips_are_counted_from_the_reset() {
+        let path = std::env::temp_dir().join(format!("ants_trips_{}.jsonl", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        let mut recorder = MetricsRecorder::create(&path, 1).unwrap();
+
+        let mut stats = WorldStats {
+            trips_completed: 4,
+            trip_ticks_total: 400,
+            ..WorldStats::default()
+        };
+        recorder.sample(&stats).unwrap();
+
+        // The new world has fewer trips than the old one had
+        recorder.reset();
+        stats.trips_completed = 1;
+        stats.trip_ticks_total = 30;
+        recorder.sample(&stats).unwrap();
+        recorder.flush().unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let row: serde_json::Value = serde_json::from_str(text.lines().last().unwrap()).unwrap();
+        assert_eq!(row["trips"], 1);
+        assert_eq!(row["mean_trip_length"], 30.0);
+        assert_eq!(row["run"], 1);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::{
+    ant::{state_name, NUM_STATES},
+    renderer::{Corner, Renderer},
+    stats::WorldStats,
+};
+
+/// Text panels drawn on top of the world
+pub struct Overlay {
+    visible: bool,
+}
+
+impl Overlay {
+    pub fn new(visible: bool) -> Self {
+        return Self { visible: visible };
+    }
+
+    pub fn is_visible(&self) -> bool {
+        return self.visible;
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn stat_lines(stats: &WorldStats, fps: u32) -> Vec<String> {
+        let mut lines = vec![
+            format!("tick {}   fps {}", stats.tick, fps),
+            format!("ants {}", stats.num_ants),
+            format!("  born {}  died {}", stats.births, stats.deaths),
+        ];
+
+        for state in 0..NUM_STATES {
+            lines.push(format!(
+                "  {} {}",
+                state_name(state as u8),
+                stats.ants_per_state[state]
+            ));
+        }
+
+        lines.push(format!("food collected {}", stats.food_collected));
+        lines.push(format!("food stored {}", stats.food_stored));
+        lines.push(format!("food carried {}", stats.food_carried));
+        lines.push(format!("food remaining {}", stats.food_remaining));
+        lines.push(format!(
+            "food sources {} ({} depleted)",
+            stats.food_sources_active + stats.food_sources_depleted,
+            stats.food_sources_depleted
+        ));
+        lines.push(format!("to-home mass {:.0}", stats.to_home_mass));
+        lines.push(format!("to-food mass {:.0}", stats.to_food_mass));
+
+        return lines;
+    }
+
+    pub fn render<R: Renderer>(&self, renderer: &mut R, lines: &[String], corner: Corner) {
+        if self.visible {
+            renderer.draw_text(lines, corner);
+        }
+    }
+}
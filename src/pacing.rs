@@ -0,0 +1,76 @@
+use std::time::{Duration, Instant};
+
+// Time between steps of the simulation
+const STEP_TIME: Duration = Duration::from_millis(25);
+const MAX_TICKS_PER_STEP: u32 = 64;
+
+/// Pause and speed of a viewer, shared by the window and the terminal front-end.
+/// Every step runs `ticks_per_step` ticks, speed doubles or halves with each key press.
+pub struct Pacing {
+    paused: bool,
+    paused_since: Instant,
+    ticks_per_step: u32,
+    last_step: Instant,
+}
+
+impl Pacing {
+    pub fn new() -> Self {
+        return Self {
+            paused: false,
+            paused_since: Instant::now(),
+            ticks_per_step: 1,
+            last_step: Instant::now(),
+        };
+    }
+
+    /// Returns how long the pause lasted when resuming
+    pub fn toggle_pause(&mut self) -> Option<Duration> {
+        if self.paused {
+            self.paused = false;
+            return Some(self.paused_since.elapsed());
+        }
+
+        self.pause();
+        return None;
+    }
+
+    pub fn pause(&mut self) {
+        if !self.paused {
+            self.paused = true;
+            self.paused_since = Instant::now();
+        }
+    }
+
+    /// How long the current pause has lasted so far
+    pub fn paused_for(&self) -> Option<Duration> {
+        if self.paused {
+            return Some(self.paused_since.elapsed());
+        }
+        return None;
+    }
+
+    pub fn faster(&mut self) {
+        self.ticks_per_step = (self.ticks_per_step * 2).min(MAX_TICKS_PER_STEP);
+    }
+
+    pub fn slower(&mut self) {
+        self.ticks_per_step = (self.ticks_per_step / 2).max(1);
+    }
+
+    /// Ticks to run now, 0 while paused or until the next step is due
+    pub fn take_step(&mut self) -> u32 {
+        if self.paused || self.last_step.elapsed() < STEP_TIME {
+            return 0;
+        }
+
+        self.last_step = Instant::now();
+        return self.ticks_per_step;
+    }
+
+    pub fn label(&self) -> String {
+        if self.paused {
+            return "paused".to_string();
+        }
+        return format!("speed x{}", self.ticks_per_step);
+    }
+}
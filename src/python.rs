@@ -0,0 +1,345 @@
+// Raised by the code pyo3 generates for methods returning PyResult
+#![allow(clippy::useless_conversion)]
+
+use numpy::{
+    ndarray::{Array1, Array2},
+    Element, IntoPyArray, PyArray1, PyArray2, PyArrayMethods,
+};
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyDict, PyList, PyTuple},
+};
+
+use crate::{
+    ant::{state_name, Ant, NUM_STATES},
+    command::Command,
+    config::Config,
+    stats::STAT_NAMES,
+    tile::{Terrain, Tile},
+    traits::AntTraits,
+    world::World,
+};
+
+// Keys missing from the dict fall back to these, so the module works without the data folder
+const DEFAULT_CONFIG: &str = include_str!("../data/config.ini");
+
+/// Config values as they would be written in the file: numbers as is, sequences comma separated
+fn config_text(value: &Bound<PyAny>) -> PyResult<String> {
+    if let Ok(flag) = value.extract::<bool>() {
+        return Ok((flag as u8).to_string());
+    }
+    if let Ok(number) = value.extract::<i64>() {
+        return Ok(number.to_string());
+    }
+    if let Ok(number) = value.extract::<f64>() {
+        return Ok(number.to_string());
+    }
+    if let Ok(text) = value.extract::<String>() {
+        return Ok(text);
+    }
+    if value.is_instance_of::<PyList>() || value.is_instance_of::<PyTuple>() {
+        let words = value
+            .iter()?
+            .map(|item| config_text(&item?))
+            .collect::<PyResult<Vec<String>>>()?;
+        return Ok(words.join(","));
+    }
+
+    return Err(PyValueError::new_err(format!(
+        "unsupported config value {}",
+        value
+    )));
+}
+
+/// Simulation without a window. Grids come back as (height, width) arrays, so they can
+/// go straight into imshow. Grid arrays are views that step and apply keep up to date,
+/// the ant arrays are copies since the number of ants changes.
+#[pyclass(name = "World", unsendable)]
+struct PyWorld {
+    world: World,
+    views: GridViews,
+}
+
+// Arrays handed out for the grids, created on first use and refilled in place
+#[derive(Default)]
+struct GridViews {
+    to_home: Option<Py<PyArray2<f64>>>,
+    to_food: Option<Py<PyArray2<f64>>>,
+    food: Option<Py<PyArray2<u32>>>,
+    terrain: Option<Py<PyArray2<u8>>>,
+}
+
+#[pymethods]
+impl PyWorld {
+    /// Keys override the default config, e.g. {"num_ants": 500, "grid_dim": (80, 60), "seed": 7}
+    #[new]
+    #[pyo3(signature = (config = None))]
+    fn new(config: Option<&Bound<PyDict>>) -> PyResult<Self> {
+        let mut parsed = Config::parse(DEFAULT_CONFIG);
+        if let Some(config) = config {
+            for (key, value) in config.iter() {
+                parsed.set_parameter(&key.extract::<String>()?, &config_text(&value)?);
+            }
+        }
+
+        // Checked up front, World::from_config would end the interpreter on a broken distribution
+        AntTraits::parse(&parsed).map_err(PyValueError::new_err)?;
+
+        return Ok(Self {
+            world: World::from_config(&parsed),
+            views: GridViews::default(),
+        });
+    }
+
+    #[pyo3(signature = (n = 1))]
+    fn step(&mut self, py: Python, n: u64) {
+        for _ in 0..n {
+            self.world.update();
+        }
+        self.refresh_views(py);
+    }
+
+    /// Edit written like a replay line, e.g. "place_food 10 12 2 500"
+    fn apply(&mut self, py: Python, command: &str) -> PyResult<()> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        let command = Command::parse(&words).map_err(PyValueError::new_err)?;
+        self.world.apply(&command).map_err(PyValueError::new_err)?;
+        self.refresh_views(py);
+        return Ok(());
+    }
+
+    #[getter]
+    fn tick(&self) -> u64 {
+        return self.world.tick();
+    }
+
+    #[getter]
+    fn seed(&self) -> u64 {
+        return self.world.seed();
+    }
+
+    /// (width, height) in tiles
+    #[getter]
+    fn grid_size(&self) -> (u32, u32) {
+        return self.world.grid_size();
+    }
+
+    /// Strength of the trail leading home
+    #[pyo3(name = "to_home")]
+    fn home_trail<'py>(&mut self, py: Python<'py>) -> Bound<'py, PyArray2<f64>> {
+        let world = &self.world;
+        return view(py, world, &mut self.views.to_home, to_home_value);
+    }
+
+    /// Strength of the trail leading to food
+    #[pyo3(name = "to_food")]
+    fn food_trail<'py>(&mut self, py: Python<'py>) -> Bound<'py, PyArray2<f64>> {
+        let world = &self.world;
+        return view(py, world, &mut self.views.to_food, to_food_value);
+    }
+
+    fn food<'py>(&mut self, py: Python<'py>) -> Bound<'py, PyArray2<u32>> {
+        let world = &self.world;
+        return view(py, world, &mut self.views.food, food_value);
+    }
+
+    /// 1 for walls, 0 for open ground
+    fn terrain<'py>(&mut self, py: Python<'py>) -> Bound<'py, PyArray2<u8>> {
+        let world = &self.world;
+        return view(py, world, &mut self.views.terrain, terrain_value);
+    }
+
+    /// (n, 2) array of x, y in world units
+    fn ant_positions<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f64>> {
+        let ants = self.world.ants();
+        let positions = Array2::from_shape_fn((ants.len(), 2), |(index, axis)| {
+            let pos = ants[index].pos();
+            return if axis == 0 { pos.x } else { pos.y };
+        });
+        return positions.into_pyarray_bound(py);
+    }
+
+    /// Index into state_names for every ant
+    fn ant_states<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<u8>> {
+        return self.ant_array(|ant| ant.state()).into_pyarray_bound(py);
+    }
+
+    fn ant_castes<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<u64>> {
+        return self
+            .ant_array(|ant| ant.caste() as u64)
+            .into_pyarray_bound(py);
+    }
+
+    fn ant_energy<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        return self.ant_array(|ant| ant.energy()).into_pyarray_bound(py);
+    }
+
+    fn ant_carrying<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<u32>> {
+        return self.ant_array(|ant| ant.carrying()).into_pyarray_bound(py);
+    }
+
+    #[getter]
+    fn state_names(&self) -> Vec<&'static str> {
+        return (0..NUM_STATES)
+            .map(|state| state_name(state as u8))
+            .collect();
+    }
+
+    /// Same numbers as the metrics file, plus the ants per state keyed by state name
+    fn stats<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let stats = self.world.stats();
+        let dict = PyDict::new_bound(py);
+
+        for name in STAT_NAMES.iter() {
+            dict.set_item(*name, stats.value(name).unwrap_or(0.0))?;
+        }
+
+        let per_state = PyDict::new_bound(py);
+        for state in 0..NUM_STATES {
+            per_state.set_item(state_name(state as u8), stats.ants_per_state[state])?;
+        }
+        dict.set_item("ants_per_state", per_state)?;
+
+        return Ok(dict);
+    }
+
+    fn ant_hill<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let ant_hill = self.world.ant_hill();
+        let dict = PyDict::new_bound(py);
+
+        dict.set_item("pos", (ant_hill.pos.x, ant_hill.pos.y))?;
+        dict.set_item("radius", ant_hill.radius())?;
+        dict.set_item("food_collected", ant_hill.food_collected)?;
+        dict.set_item("food_stored", ant_hill.food_stored)?;
+        dict.set_item("trips_completed", ant_hill.trips_completed)?;
+        dict.set_item("trip_ticks_total", ant_hill.trip_ticks_total)?;
+
+        return Ok(dict);
+    }
+}
+
+impl PyWorld {
+    // Views the caller still holds show the new state without asking again
+    fn refresh_views(&self, py: Python) {
+        let views = &self.views;
+        refill(py, &self.world, &views.to_home, to_home_value);
+        refill(py, &self.world, &views.to_food, to_food_value);
+        refill(py, &self.world, &views.food, food_value);
+        refill(py, &self.world, &views.terrain, terrain_value);
+    }
+
+    fn ant_array<T, F: Fn(&Ant) -> T>(&self, value: F) -> Array1<T> {
+        return self.world.ants().iter().map(value).collect();
+    }
+}
+
+fn to_home_value(tile: &Tile) -> f64 {
+    return tile.to_home_strength();
+}
+
+fn to_food_value(tile: &Tile) -> f64 {
+    return tile.to_food_strength();
+}
+
+fn food_value(tile: &Tile) -> u32 {
+    return tile.food.concentration;
+}
+
+fn terrain_value(tile: &Tile) -> u8 {
+    return (tile.terrain == Terrain::Wall) as u8;
+}
+
+fn grid_array<T, F: Fn(&Tile) -> T>(world: &World, value: F) -> Array2<T> {
+    let (width, height) = world.grid_size();
+    return Array2::from_shape_fn((height as usize, width as usize), |(y, x)| {
+        return value(world.tile((x as u32, y as u32)));
+    });
+}
+
+// The view of a grid, created from the current tiles the first time it is asked for
+fn view<'py, T: Element, F: Fn(&Tile) -> T>(
+    py: Python<'py>,
+    world: &World,
+    slot: &mut Option<Py<PyArray2<T>>>,
+    value: F,
+) -> Bound<'py, PyArray2<T>> {
+    return slot
+        .get_or_insert_with(|| grid_array(world, value).into_pyarray_bound(py).unbind())
+        .bind(py)
+        .clone();
+}
+
+// Writes the current tiles into a view handed out before
+fn refill<T: Element, F: Fn(&Tile) -> T>(
+    py: Python,
+    world: &World,
+    slot: &Option<Py<PyArray2<T>>>,
+    value: F,
+) {
+    let array = match slot {
+        Some(array) => array.bind(py),
+        None => return,
+    };
+
+    // Skipped if the caller made the array read only
+    if let Ok(mut array) = array.try_readwrite() {
+        for ((y, x), cell) in array.as_array_mut().indexed_iter_mut() {
+            *cell = value(world.tile((x as u32, y as u32)));
+        }
+    }
+}
+
+/// `import ants`, built with `maturin develop`
+#[pymodule]
+fn ants(module: &Bound<PyModule>) -> PyResult<()> {
+    module.add_class::<PyWorld>()?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::IntoPyDict;
+
+    #[test]
+    fn python_values_become_config_text() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let text = |code: &str| -> PyResult<String> {
+                return config_text(&py.eval_bound(code, None, None).unwrap());
+            };
+
+            assert_eq!(text("True").unwrap(), "1");
+            assert_eq!(text("500").unwrap(), "500");
+            assert_eq!(text("0.25").unwrap(), "0.25");
+            assert_eq!(text("'clusters'").unwrap(), "clusters");
+            assert_eq!(text("(80, 60)").unwrap(), "80,60");
+            assert_eq!(text("['normal', 5, 1.5]").unwrap(), "normal,5,1.5");
+            assert!(text("{'a': 1}").is_err());
+        });
+    }
+
+    #[test]
+    fn dict_keys_override_the_default_config() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let config = [
+                ("num_ants", 5.into_py(py)),
+                ("grid_dim", (30, 20).into_py(py)),
+                ("seed", 7.into_py(py)),
+                ("ant_speed", ("fixed", 2).into_py(py)),
+            ]
+            .into_py_dict_bound(py);
+            let world = PyWorld::new(Some(&config)).unwrap();
+
+            assert_eq!(world.world.ants().len(), 5);
+            assert_eq!(world.grid_size(), (30, 20));
+            assert_eq!(world.seed(), 7);
+            assert!(world.world.ants().iter().all(|ant| ant.speed() == 2.0));
+
+            let broken = [("ant_speed", "gamma,1,2")].into_py_dict_bound(py);
+            assert!(PyWorld::new(Some(&broken)).is_err());
+        });
+    }
+}
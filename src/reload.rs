@@ -0,0 +1,152 @@
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{config::Config, world::World};
+
+/// Parameters only the viewer reads, they apply without touching the world
+pub const VIEW_PARAMETERS: [&str; 3] = ["background_color", "layer_marker_max", "layer_food_max"];
+
+/// How a changed parameter reaches a running viewer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterKind {
+    /// Applied to the running world, see World::is_live_parameter
+    Live,
+    View,
+    /// Needs a new world, e.g. grid_dim or num_ants
+    Structural,
+}
+
+pub fn parameter_kind(name: &str) -> ParameterKind {
+    if World::is_live_parameter(name) {
+        return ParameterKind::Live;
+    }
+    if VIEW_PARAMETERS.contains(&name) {
+        return ParameterKind::View;
+    }
+    return ParameterKind::Structural;
+}
+
+/// Names of parameters added, removed or changed between two configs, sorted
+pub fn changed_parameters(old: &Config, new: &Config) -> Vec<String> {
+    let mut names: Vec<String> = old
+        .parameters
+        .keys()
+        .chain(new.parameters.keys())
+        .filter(|name| {
+            return old.parameters.get(*name).map(|parameter| &parameter.text)
+                != new.parameters.get(*name).map(|parameter| &parameter.text);
+        })
+        .cloned()
+        .collect();
+
+    names.sort();
+    names.dedup();
+
+    return names;
+}
+
+/// Polls the modification time of the config file, cheaper than a file system watcher
+/// and good enough for edits by hand
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+
+    interval: Duration,
+    last_poll: Instant,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: PathBuf, interval: Duration) -> Self {
+        return Self {
+            modified: ConfigWatcher::modified(&path),
+            path: path,
+
+            interval: interval,
+            last_poll: Instant::now(),
+        };
+    }
+
+    /// The new config once the file changed, checked at most every interval
+    pub fn poll(&mut self) -> Option<Config> {
+        if self.last_poll.elapsed() < self.interval {
+            return None;
+        }
+        self.last_poll = Instant::now();
+
+        let modified = ConfigWatcher::modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        return match fs::read_to_string(&self.path) {
+            Ok(content) => Some(Config::parse(&content)),
+            Err(e) => {
+                log::error!("config reload error: {}", &e);
+                None
+            }
+        };
+    }
+
+    fn modified(path: &PathBuf) -> Option<SystemTime> {
+        return fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parameters_are_sorted_by_how_they_apply() {
+        assert_eq!(parameter_kind("pheromone_decay"), ParameterKind::Live);
+        assert_eq!(parameter_kind("ant_speed"), ParameterKind::Live);
+        assert_eq!(
+            parameter_kind("caste_soldier_marker_strength"),
+            ParameterKind::Live
+        );
+        assert_eq!(parameter_kind("nest_color"), ParameterKind::Live);
+        assert_eq!(parameter_kind("background_color"), ParameterKind::View);
+        assert_eq!(parameter_kind("grid_dim"), ParameterKind::Structural);
+        assert_eq!(parameter_kind("ant_castes"), ParameterKind::Structural);
+        assert_eq!(
+            parameter_kind("caste_worker_share"),
+            ParameterKind::Structural
+        );
+    }
+
+    #[test]
+    fn changes_include_added_and_removed_parameters() {
+        let old = Config::parse("a 1\nb 2\nc 3\n");
+        let new = Config::parse("a 1\nb 5\nd 4\n");
+
+        assert_eq!(changed_parameters(&old, &new), vec!["b", "c", "d"]);
+        assert!(changed_parameters(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn watcher_reads_the_file_after_it_changed() {
+        let path = std::env::temp_dir().join(format!("ants_reload_{}.ini", std::process::id()));
+        fs::write(&path, "num_ants 10\n").unwrap();
+
+        let mut watcher = ConfigWatcher::new(path.clone(), Duration::ZERO);
+        assert!(watcher.poll().is_none());
+
+        // Set explicitly, the write could land within the timestamp resolution
+        fs::write(&path, "num_ants 20\n").unwrap();
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+
+        let config = watcher.poll().unwrap();
+        assert_eq!(config.get_parameter("num_ants").vals, vec![20.0]);
+        assert!(watcher.poll().is_none());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,259 @@
+use sdl2::{
+    pixels::Color,
+    rect::{Point, Rect},
+    render::{BlendMode, Canvas, TextureCreator},
+    video::{Window, WindowContext},
+};
+
+use crate::{
+    font,
+    grid::{DirtyRegion, Grid},
+    layers::LayerSettings,
+    tile_texture::TileTexture,
+};
+
+const PIXEL_SCALE: u32 = 2;
+const LINE_SPACING: u32 = 3;
+const PADDING: u32 = 8;
+
+const PANEL_COLOR: Color = Color::RGBA(0, 0, 0, 170);
+const TEXT_COLOR: Color = Color::RGBA(255, 255, 255, 255);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+}
+
+/// Tile colors of the active layer, one cell per tile
+pub struct Heatmap<'a> {
+    pub grid: &'a Grid,
+    pub layers: &'a LayerSettings,
+    /// Tiles changed since the last frame, backends keeping their last picture only redraw these
+    pub dirty: Option<DirtyRegion>,
+}
+
+impl<'a> Heatmap<'a> {
+    pub fn size(&self) -> (u32, u32) {
+        return self.grid.size();
+    }
+
+    pub fn color(&self, pos: (u32, u32)) -> Color {
+        return self.layers.tile_color(self.grid.tile(pos));
+    }
+}
+
+/// Everything the front-ends draw goes through this, positions are in output pixels
+pub trait Renderer {
+    fn output_size(&self) -> (u32, u32);
+
+    fn clear(&mut self, color: Color);
+
+    fn fill_rects(&mut self, rects: &[Rect], color: Color);
+
+    /// Rectangle outlines one pixel wide
+    fn draw_rects(&mut self, rects: &[Rect], color: Color);
+
+    fn draw_lines(&mut self, lines: &[(Point, Point)], color: Color);
+
+    /// Stretches the heatmap over dest
+    fn draw_heatmap(&mut self, heatmap: &Heatmap, dest: Rect);
+
+    /// Panel of text lines in a corner of the output, drawn with the bitmap font
+    fn draw_text(&mut self, lines: &[String], corner: Corner) {
+        if lines.is_empty() {
+            return;
+        }
+
+        let line_height = (font::GLYPH_HEIGHT + LINE_SPACING) * PIXEL_SCALE;
+        let panel_width = lines
+            .iter()
+            .map(|line| font::text_width(line))
+            .max()
+            .unwrap_or(0)
+            * PIXEL_SCALE
+            + PADDING * 2;
+        let panel_height = lines.len() as u32 * line_height + PADDING * 2;
+
+        let (output_width, output_height) = self.output_size();
+        let (panel_x, panel_y) = match corner {
+            Corner::TopLeft => (0, 0),
+            Corner::TopRight => (output_width as i32 - panel_width as i32, 0),
+            Corner::BottomLeft => (0, output_height as i32 - panel_height as i32),
+        };
+
+        self.fill_rects(
+            &[Rect::new(panel_x, panel_y, panel_width, panel_height)],
+            PANEL_COLOR,
+        );
+
+        let mut pixel_rects: Vec<Rect> = Vec::new();
+
+        for (index, line) in lines.iter().enumerate() {
+            let origin_y = panel_y + (PADDING + index as u32 * line_height) as i32;
+
+            font::for_each_pixel(line, |x, y| {
+                pixel_rects.push(Rect::new(
+                    panel_x + (PADDING + x * PIXEL_SCALE) as i32,
+                    origin_y + (y * PIXEL_SCALE) as i32,
+                    PIXEL_SCALE,
+                    PIXEL_SCALE,
+                ));
+            });
+        }
+
+        self.fill_rects(&pixel_rects, TEXT_COLOR);
+    }
+}
+
+/// Draws into the window. The heatmap lives in a streaming texture that only gets
+/// the dirty tiles uploaded.
+pub struct SdlRenderer<'a> {
+    canvas: Canvas<Window>,
+    texture_creator: &'a TextureCreator<WindowContext>,
+    tile_texture: Option<TileTexture<'a>>,
+}
+
+impl<'a> SdlRenderer<'a> {
+    pub fn new(
+        mut canvas: Canvas<Window>,
+        texture_creator: &'a TextureCreator<WindowContext>,
+    ) -> Self {
+        canvas.set_blend_mode(BlendMode::Blend);
+
+        return Self {
+            canvas: canvas,
+            texture_creator: texture_creator,
+            tile_texture: None,
+        };
+    }
+
+    pub fn canvas(&self) -> &Canvas<Window> {
+        return &self.canvas;
+    }
+
+    pub fn present(&mut self) {
+        self.canvas.present();
+    }
+
+    fn report(result: Result<(), String>) {
+        match result {
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("render error: {}", &e);
+            }
+        }
+    }
+}
+
+impl<'a> Renderer for SdlRenderer<'a> {
+    fn output_size(&self) -> (u32, u32) {
+        return self.canvas.output_size().unwrap_or((0, 0));
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.canvas.set_draw_color(color);
+        self.canvas.clear();
+    }
+
+    fn fill_rects(&mut self, rects: &[Rect], color: Color) {
+        self.canvas.set_draw_color(color);
+        SdlRenderer::report(self.canvas.fill_rects(rects));
+    }
+
+    fn draw_rects(&mut self, rects: &[Rect], color: Color) {
+        self.canvas.set_draw_color(color);
+        SdlRenderer::report(self.canvas.draw_rects(rects));
+    }
+
+    fn draw_lines(&mut self, lines: &[(Point, Point)], color: Color) {
+        self.canvas.set_draw_color(color);
+        for (from, to) in lines {
+            SdlRenderer::report(self.canvas.draw_line(*from, *to));
+        }
+    }
+
+    fn draw_heatmap(&mut self, heatmap: &Heatmap, dest: Rect) {
+        let size = heatmap.size();
+
+        // A new or resized texture has no picture yet, so it takes every tile
+        let mut dirty = heatmap.dirty;
+        if self.tile_texture.as_ref().map(|texture| texture.size()) != Some(size) {
+            match TileTexture::new(self.texture_creator, size) {
+                Ok(texture) => self.tile_texture = Some(texture),
+                Err(e) => {
+                    log::error!("texture error: {}", &e);
+                    return;
+                }
+            }
+            dirty = Some(DirtyRegion {
+                min: (0, 0),
+                max: (size.0 - 1, size.1 - 1),
+            });
+        }
+
+        let tile_texture = self.tile_texture.as_mut().unwrap();
+        if let Some(region) = dirty {
+            tile_texture.update(heatmap, region);
+        }
+        SdlRenderer::report(tile_texture.render(&mut self.canvas, dest));
+    }
+}
+
+/// Draws nothing, for runs that need a renderer but no picture
+pub struct NullRenderer {
+    size: (u32, u32),
+}
+
+impl NullRenderer {
+    pub fn new(size: (u32, u32)) -> Self {
+        return Self { size: size };
+    }
+}
+
+impl Renderer for NullRenderer {
+    fn output_size(&self) -> (u32, u32) {
+        return self.size;
+    }
+
+    fn clear(&mut self, _color: Color) {}
+
+    fn fill_rects(&mut self, _rects: &[Rect], _color: Color) {}
+
+    fn draw_rects(&mut self, _rects: &[Rect], _color: Color) {}
+
+    fn draw_lines(&mut self, _lines: &[(Point, Point)], _color: Color) {}
+
+    fn draw_heatmap(&mut self, _heatmap: &Heatmap, _dest: Rect) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{draw::draw_world, fixture, overlay::Overlay, viewport::Viewport};
+
+    #[test]
+    fn null_renderer_takes_a_whole_frame() {
+        let mut world = fixture::world();
+        let mut renderer = NullRenderer::new((320, 240));
+        let viewport = Viewport::new(world.world_size(), renderer.output_size());
+
+        let dirty = world.take_dirty_tiles();
+        assert!(dirty.is_some());
+
+        renderer.clear(Color::BLACK);
+        draw_world(
+            &mut renderer,
+            &world,
+            dirty,
+            &viewport,
+            &LayerSettings::new(1000.0, 100.0),
+            Color::RGB(10, 10, 10),
+        );
+        renderer.draw_text(&Overlay::stat_lines(&world.stats(), 60), Corner::TopLeft);
+
+        assert_eq!(renderer.output_size(), (320, 240));
+        assert!(world.take_dirty_tiles().is_none());
+    }
+}
@@ -0,0 +1,347 @@
+use std::fs;
+
+use crate::{command::Command, config::Config};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplayEvent {
+    Command(Command),
+    /// The viewer was paused for this long before the tick ran
+    Pause {
+        millis: u64,
+    },
+}
+
+/// A recorded run: the seed and config it started from and every edit with the tick it
+/// was applied before. The map file is embedded with its number of lines, so the replay
+/// doesn't depend on it. Saved as text, so it can be shared and read:
+///
+/// ```text
+/// replay 1
+/// seed 42
+/// config
+/// num_ants 1000
+/// ...
+/// file example.map 2
+/// nest 50 50 15
+/// food 20 20 2500
+/// events
+/// 120 place_food 30 40 2 2000
+/// 300 pause 1500
+/// end 5000
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub config: String,
+    /// Path and contents of the files the config refers to
+    pub files: Vec<(String, String)>,
+    pub events: Vec<(u64, ReplayEvent)>,
+    /// Tick the recording stopped at
+    pub end_tick: u64,
+}
+
+impl Replay {
+    pub fn new(seed: u64, config: &Config) -> Self {
+        // A map that failed to load is kept empty, the run went without it as well
+        let mut files: Vec<(String, String)> = Vec::new();
+        if config.has_parameter("map_file") {
+            let path = config.get_words("map_file")[0].clone();
+            if path != "none" {
+                let content = config.read_file(&path).unwrap_or_default();
+                let lines: String = content.lines().map(|line| format!("{}\n", line)).collect();
+                files.push((path, lines));
+            }
+        }
+
+        return Self {
+            seed: seed,
+            config: config.to_text(),
+            files: files,
+            events: Vec::new(),
+            end_tick: 0,
+        };
+    }
+
+    pub fn record(&mut self, tick: u64, event: ReplayEvent) {
+        self.events.push((tick, event));
+    }
+
+    /// Config to rebuild the world with, with the seed fixed
+    pub fn config(&self) -> Config {
+        let mut config = Config::parse(&self.config);
+        config.set_parameter("seed", &self.seed.to_string());
+        for (path, content) in self.files.iter() {
+            config.set_file(path, content);
+        }
+        return config;
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("replay 1\nseed {}\nconfig\n", self.seed);
+        text.push_str(&self.config);
+        for (path, content) in self.files.iter() {
+            text.push_str(&format!("file {} {}\n", path, content.lines().count()));
+            text.push_str(content);
+        }
+        text.push_str("events\n");
+
+        for (tick, event) in self.events.iter() {
+            let line = match event {
+                ReplayEvent::Command(command) => command.to_line(),
+                ReplayEvent::Pause { millis } => format!("pause {}", millis),
+            };
+            text.push_str(&format!("{} {}\n", tick, line));
+        }
+
+        text.push_str(&format!("end {}\n", self.end_tick));
+        return text;
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines();
+
+        if lines.next() != Some("replay 1") {
+            return Err("not a replay file".to_string());
+        }
+
+        let seed = match lines.next().and_then(|line| line.strip_prefix("seed ")) {
+            Some(seed) => seed.parse::<u64>().map_err(|e| e.to_string())?,
+            None => return Err("missing seed".to_string()),
+        };
+
+        if lines.next() != Some("config") {
+            return Err("missing config".to_string());
+        }
+
+        let mut config = String::new();
+        let mut files: Vec<(String, String)> = Vec::new();
+        loop {
+            match lines.next() {
+                Some("events") => break,
+                Some(line) if line.starts_with("file ") => {
+                    let words: Vec<&str> = line.split_whitespace().collect();
+                    let num_lines = match words[..] {
+                        [_, _, count] => count
+                            .parse::<usize>()
+                            .map_err(|_| format!("invalid line count in '{}'", line))?,
+                        _ => return Err(format!("expected 'file path lines', got '{}'", line)),
+                    };
+
+                    let mut content = String::new();
+                    for _ in 0..num_lines {
+                        match lines.next() {
+                            Some(file_line) => {
+                                content.push_str(file_line);
+                                content.push('\n');
+                            }
+                            None => return Err(format!("{} ends early", words[1])),
+                        }
+                    }
+                    files.push((words[1].to_string(), content));
+                }
+                Some(line) => {
+                    config.push_str(line);
+                    config.push('\n');
+                }
+                None => return Err("missing events".to_string()),
+            }
+        }
+
+        let mut replay = Self {
+            seed: seed,
+            config: config,
+            files: files,
+            events: Vec::new(),
+            end_tick: 0,
+        };
+
+        for line in lines {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if words.is_empty() {
+                continue;
+            }
+
+            let number = |word: &str| -> Result<u64, String> {
+                return word
+                    .parse::<u64>()
+                    .map_err(|_| format!("invalid number in '{}'", line));
+            };
+
+            if words[0] == "end" && words.len() == 2 {
+                replay.end_tick = number(words[1])?;
+                continue;
+            }
+
+            let tick = number(words[0])?;
+            let event = match words.get(1) {
+                Some(&"pause") if words.len() == 3 => ReplayEvent::Pause {
+                    millis: number(words[2])?,
+                },
+                _ => ReplayEvent::Command(Command::parse(&words[1..])?),
+            };
+            replay.events.push((tick, event));
+        }
+
+        return Ok(replay);
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        return Replay::parse(&text);
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        return fs::write(path, self.to_text()).map_err(|e| format!("{}: {}", path, e));
+    }
+}
+
+/// Hands out the recorded events tick by tick
+pub struct ReplayPlayer {
+    events: Vec<(u64, ReplayEvent)>,
+    next: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: &Replay) -> Self {
+        return Self {
+            events: replay.events.clone(),
+            next: 0,
+        };
+    }
+
+    /// Events to apply before the given tick runs
+    pub fn due(&mut self, tick: u64) -> Vec<ReplayEvent> {
+        let mut due: Vec<ReplayEvent> = Vec::new();
+
+        while self.next < self.events.len() && self.events[self.next].0 <= tick {
+            due.push(self.events[self.next].1);
+            self.next += 1;
+        }
+
+        return due;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixture, world::World};
+
+    #[test]
+    fn text_round_trips() {
+        let mut replay = Replay::new(42, &fixture::config());
+        replay.record(
+            3,
+            ReplayEvent::Command(Command::PaintMarker {
+                pos: (4, 5),
+                radius: 1,
+                m_type: 2,
+                strength: 123.456,
+            }),
+        );
+        replay.record(3, ReplayEvent::Pause { millis: 800 });
+        replay.record(9, ReplayEvent::Command(Command::MoveHill { pos: (7, 8) }));
+        replay.record(
+            12,
+            ReplayEvent::Command(Command::PlaceWallLine {
+                from: (0, 3),
+                to: (10, 6),
+            }),
+        );
+        replay.record(
+            15,
+            ReplayEvent::Command(Command::RemoveNearestFood { pos: (2, 2) }),
+        );
+        replay.end_tick = 20;
+
+        assert_eq!(Replay::parse(&replay.to_text()), Ok(replay));
+    }
+
+    #[test]
+    fn the_map_travels_with_the_replay() {
+        let path = std::env::temp_dir().join(format!("ants_replay_{}.map", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        std::fs::write(&path, "# test map\nnest 10 12\nfood 30 30 200 1\n").unwrap();
+
+        let mut config = fixture::config();
+        config.set_parameter("food_layout", "file");
+        config.set_parameter("map_file", &path);
+        let world = World::from_config(&config);
+        let mut recording = Replay::new(world.seed(), &config);
+        recording.end_tick = 10;
+
+        // Neither a changed nor a missing map file matters once it is recorded
+        std::fs::write(&path, "food 5 5 1\n").unwrap();
+        let replay = Replay::parse(&recording.to_text()).unwrap();
+        assert_eq!(replay, recording);
+        std::fs::remove_file(&path).unwrap();
+
+        let replayed = World::from_config(&replay.config());
+        assert_eq!(replayed.ant_hill().pos, world.ant_hill().pos);
+        assert_eq!(replayed.stats().food_remaining, 200);
+        assert_eq!(
+            replayed.tile((30, 30)).food.concentration,
+            world.tile((30, 30)).food.concentration
+        );
+        assert_eq!(replayed.tile((5, 5)).food.concentration, 0);
+    }
+
+    #[test]
+    fn replaying_reproduces_the_run() {
+        let edits = [
+            (
+                5,
+                Command::PlaceFood {
+                    pos: (24, 20),
+                    radius: 1,
+                    amount: 30,
+                },
+            ),
+            (
+                5,
+                Command::PlaceWall {
+                    pos: (10, 10),
+                    radius: 2,
+                },
+            ),
+            (40, Command::MoveHill { pos: (15, 25) }),
+        ];
+
+        let config = fixture::config();
+        let mut world = World::from_config(&config);
+        let mut recording = Replay::new(world.seed(), &config);
+
+        for _ in 0..80 {
+            for (tick, command) in edits.iter() {
+                if *tick == world.tick() {
+                    world.apply(command).unwrap();
+                    recording.record(world.tick(), ReplayEvent::Command(*command));
+                }
+            }
+            world.update();
+        }
+        recording.end_tick = world.tick();
+
+        let replay = Replay::parse(&recording.to_text()).unwrap();
+        let mut replayed = World::from_config(&replay.config());
+        let mut player = ReplayPlayer::new(&replay);
+
+        while replayed.tick() < replay.end_tick {
+            for event in player.due(replayed.tick()) {
+                if let ReplayEvent::Command(command) = event {
+                    replayed.apply(&command).unwrap();
+                }
+            }
+            replayed.update();
+        }
+
+        assert_eq!(
+            replayed.stats().food_remaining,
+            world.stats().food_remaining
+        );
+        assert_eq!(
+            replayed.ant(0).map(|ant| ant.pos()),
+            world.ant(0).map(|ant| ant.pos())
+        );
+    }
+}
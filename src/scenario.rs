@@ -0,0 +1,259 @@
+use std::fs;
+
+use crate::{
+    args::flag_value,
+    command::Command,
+    stats::{WorldStats, STAT_NAMES},
+    world::World,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+}
+
+impl Comparison {
+    fn parse(word: &str) -> Option<Self> {
+        return match word {
+            "<" => Some(Comparison::Less),
+            "<=" => Some(Comparison::LessOrEqual),
+            ">" => Some(Comparison::Greater),
+            ">=" => Some(Comparison::GreaterOrEqual),
+            "==" => Some(Comparison::Equal),
+            _ => None,
+        };
+    }
+
+    fn holds(&self, left: f64, right: f64) -> bool {
+        return match *self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Equal => left == right,
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Trigger {
+    At(u64),
+    /// Compares one of the STAT_NAMES with a number
+    When {
+        stat: String,
+        comparison: Comparison,
+        value: f64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Action {
+    trigger: Trigger,
+    /// Command words, "nest" stands for the grid position of the nest when it fires
+    words: Vec<String>,
+    fired: bool,
+}
+
+impl Action {
+    fn command(&self, nest: (u32, u32)) -> Result<Command, String> {
+        let (x, y) = (nest.0.to_string(), nest.1.to_string());
+
+        let mut words: Vec<&str> = Vec::new();
+        for word in self.words.iter() {
+            if word == "nest" {
+                words.push(&x);
+                words.push(&y);
+            } else {
+                words.push(word);
+            }
+        }
+
+        return Command::parse(&words);
+    }
+}
+
+/// Scripted edits for an experiment, each action runs once before the first tick
+/// its trigger is met. Commands are written like in replay files, positions
+/// are grid coordinates and "nest" can stand in for one:
+///
+/// ```text
+/// # food to the north-east once the run settled
+/// at 500 place_food 80 20 2 500
+/// at 2000 remove_nearest_food nest
+/// at 0 place_wall_line 40 0 40 60
+/// when food_collected > 10000 move_hill 20 80
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    actions: Vec<Action>,
+}
+
+impl Scenario {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut actions: Vec<Action> = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let action =
+                Scenario::parse_action(line).map_err(|e| format!("line {}: {}", index + 1, e))?;
+            actions.push(action);
+        }
+
+        return Ok(Self { actions: actions });
+    }
+
+    fn parse_action(line: &str) -> Result<Action, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        let (trigger, command_words) = match words[0] {
+            "at" if words.len() > 2 => {
+                let tick = words[1]
+                    .parse::<u64>()
+                    .map_err(|_| format!("invalid tick '{}'", words[1]))?;
+                (Trigger::At(tick), &words[2..])
+            }
+            "when" if words.len() > 4 => {
+                if !STAT_NAMES.contains(&words[1]) {
+                    return Err(format!("unknown stat '{}'", words[1]));
+                }
+                let comparison = Comparison::parse(words[2])
+                    .ok_or(format!("invalid comparison '{}'", words[2]))?;
+                let value = words[3]
+                    .parse::<f64>()
+                    .map_err(|_| format!("invalid number '{}'", words[3]))?;
+
+                (
+                    Trigger::When {
+                        stat: words[1].to_string(),
+                        comparison: comparison,
+                        value: value,
+                    },
+                    &words[4..],
+                )
+            }
+            _ => {
+                return Err(format!(
+                    "expected 'at <tick>' or 'when <stat> <op> <value>' in '{}'",
+                    line
+                ))
+            }
+        };
+
+        let action = Action {
+            trigger: trigger,
+            words: command_words.iter().map(|word| word.to_string()).collect(),
+            fired: false,
+        };
+
+        // Checked now, so a typo doesn't wait until the action fires
+        action.command((0, 0))?;
+
+        return Ok(action);
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        return Scenario::parse(&text).map_err(|e| format!("{}: {}", path, e));
+    }
+
+    /// Scenario given with "--scenario file", exits if it can't be read
+    pub fn from_args(args: &[String]) -> Option<Self> {
+        return match flag_value(args, "--scenario").map(Scenario::load) {
+            Some(Ok(scenario)) => Some(scenario),
+            Some(Err(e)) => {
+                log::error!("scenario error: {}", &e);
+                std::process::exit(-1);
+            }
+            None => None,
+        };
+    }
+
+    /// Commands of the actions whose trigger is met, to apply before the next tick
+    pub fn due(&mut self, world: &World) -> Vec<Command> {
+        let mut commands: Vec<Command> = Vec::new();
+
+        // Stats walk the whole grid, so they are only gathered if a condition needs them
+        let mut stats: Option<WorldStats> = None;
+        let nest = world.world_to_grid(world.ant_hill().pos);
+
+        for action in self.actions.iter_mut().filter(|action| !action.fired) {
+            let met = match &action.trigger {
+                Trigger::At(tick) => world.tick() >= *tick,
+                Trigger::When {
+                    stat,
+                    comparison,
+                    value,
+                } => {
+                    let stats = stats.get_or_insert_with(|| world.stats());
+                    comparison.holds(stats.value(stat).unwrap_or(0.0), *value)
+                }
+            };
+            if !met {
+                continue;
+            }
+
+            action.fired = true;
+            match action.command(nest) {
+                Ok(command) => commands.push(command),
+                Err(e) => {
+                    log::error!("scenario error: {}", &e);
+                }
+            }
+        }
+
+        return commands;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixture, tile::Terrain};
+
+    #[test]
+    fn errors_name_the_line() {
+        assert_eq!(
+            Scenario::parse("# setup\nat 0 place_wall 1 1 0\nat soon place_wall 1 1 0\n"),
+            Err("line 3: invalid tick 'soon'".to_string())
+        );
+        assert!(Scenario::parse("when food_eaten > 5 move_hill 1 1").is_err());
+        assert!(Scenario::parse("at 5 place_food nest").is_err());
+    }
+
+    #[test]
+    fn actions_fire_once_when_due() {
+        let mut world = fixture::world();
+        let mut scenario = Scenario::parse(
+            "at 0 place_wall_line 0 10 39 10\n\
+             at 2 place_food 5 5 1 10\n\
+             when food_remaining > 0 remove_nearest_food nest\n",
+        )
+        .unwrap();
+
+        let mut food_seen = 0;
+        for _ in 0..5 {
+            for command in scenario.due(&world) {
+                world.apply(&command).unwrap();
+            }
+            food_seen = food_seen.max(world.stats().food_remaining);
+            world.update();
+        }
+
+        assert_eq!(world.tile((0, 10)).terrain, Terrain::Wall);
+        assert_eq!(world.tile((39, 10)).terrain, Terrain::Wall);
+        assert_eq!(world.tile((20, 11)).terrain, Terrain::Ground);
+
+        // Placed at tick 2 and removed before tick 3 ran
+        assert_eq!(food_seen, 50);
+        assert_eq!(world.stats().food_remaining, 0);
+        assert!(scenario.due(&world).is_empty());
+    }
+}
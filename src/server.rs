@@ -0,0 +1,523 @@
+use std::{
+    net::SocketAddr,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    time::Duration,
+};
+
+use futures_util::{SinkExt, StreamExt};
+use sdl2::pixels::Color;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    runtime::Runtime,
+    sync::broadcast::{self, error::RecvError},
+};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    command::Command, config::Config, draw::draw_world, frame::Frame, layers::LayerSettings,
+    stats::WorldStats, viewport::Viewport, world::World,
+};
+
+// Updates a client can fall behind by before it starts skipping them
+const UPDATE_BACKLOG: usize = 16;
+
+// How often a paused run looks for new commands
+const PAUSE_POLL: Duration = Duration::from_millis(50);
+
+// Longest request head read to tell page requests from WebSocket upgrades
+const MAX_REQUEST_HEAD: usize = 4096;
+
+/// Page served on plain HTTP requests, shows the stream and sends the basic commands
+const PAGE: &str = r#"<!DOCTYPE html>
+<html><head><title>ants</title></head>
+<body style="font-family: monospace; display: flex; gap: 16px">
+<img id="frame" style="image-rendering: pixelated; width: 600px">
+<div>
+<button onclick="send({cmd: 'pause'})">pause</button>
+<button onclick="send({cmd: 'resume'})">resume</button>
+<button onclick="send({cmd: 'snapshot'})">snapshot</button>
+<pre id="status"></pre><pre id="stats"></pre>
+</div>
+<script>
+const socket = new WebSocket("ws://" + location.host);
+const send = (command) => socket.send(JSON.stringify(command));
+socket.onmessage = (event) => {
+  if (event.data instanceof Blob) {
+    URL.revokeObjectURL(document.getElementById("frame").src);
+    document.getElementById("frame").src = URL.createObjectURL(event.data);
+    return;
+  }
+  const update = JSON.parse(event.data);
+  const id = update.type == "stats" ? "stats" : "status";
+  document.getElementById(id).textContent = JSON.stringify(update, null, 1);
+};
+</script>
+</body></html>
+"#;
+
+/// JSON commands sent by clients, e.g. `{"cmd": "drop_food", "x": 10, "y": 20, "amount": 500}`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum ClientCommand {
+    Pause,
+    Resume,
+    /// Only live parameters, see World::is_live_parameter. The value is written like in the config file
+    Set {
+        key: String,
+        value: String,
+    },
+    /// Grid position, the amount is per tile like with the food brush
+    DropFood {
+        x: u32,
+        y: u32,
+        #[serde(default)]
+        radius: u32,
+        amount: u32,
+    },
+    /// Sends stats and a frame right away
+    Snapshot,
+}
+
+/// JSON updates sent to clients. Frames go out as binary PNG messages after their stats.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Update<'a> {
+    Stats(&'a WorldStats),
+    Status { tick: u64, paused: bool },
+    Error { message: String },
+}
+
+pub struct ServerSettings {
+    /// e.g. "127.0.0.1:9000", "0.0.0.0:9000" to be reachable from the LAN
+    pub addr: String,
+    /// Ticks between updates
+    pub every: u64,
+    pub frame_size: (u32, u32),
+}
+
+/// Streams stats and frames of a headless run over WebSocket and collects commands for it.
+/// Connections are handled on their own threads, the simulation only talks to
+/// them through channels in `steer` and `publish`.
+pub struct Server {
+    settings: ServerSettings,
+    local_addr: SocketAddr,
+    paused: bool,
+
+    updates: broadcast::Sender<Message>,
+    commands: Receiver<ClientCommand>,
+
+    viewport: Viewport,
+    layers: LayerSettings,
+    background: Color,
+
+    // Stops all connections when dropped
+    _runtime: Runtime,
+}
+
+impl Server {
+    pub fn start(settings: ServerSettings, config: &Config, world: &World) -> Result<Self, String> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        let listener = runtime
+            .block_on(TcpListener::bind(&settings.addr))
+            .map_err(|e| format!("{}: {}", settings.addr, e))?;
+        let local_addr = listener.local_addr().map_err(|e| e.to_string())?;
+
+        let (updates, _) = broadcast::channel(UPDATE_BACKLOG);
+        let (command_sender, commands) = mpsc::channel();
+        runtime.spawn(accept(listener, updates.clone(), command_sender));
+
+        return Ok(Self {
+            viewport: Viewport::new(world.world_size(), settings.frame_size),
+            layers: LayerSettings::new(
+                config.get_parameter("layer_marker_max").vals[0],
+                config.get_parameter("layer_food_max").vals[0],
+            ),
+            background: Color::RGB(
+                config.get_parameter("background_color").vals[0] as u8,
+                config.get_parameter("background_color").vals[1] as u8,
+                config.get_parameter("background_color").vals[2] as u8,
+            ),
+
+            settings: settings,
+            local_addr: local_addr,
+            paused: false,
+
+            updates: updates,
+            commands: commands,
+
+            _runtime: runtime,
+        });
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        return self.local_addr;
+    }
+
+    /// Applies the commands received since the last call. Blocks while a client has
+    /// the run paused, until another one resumes it.
+    pub fn steer(&mut self, world: &mut World, config: &mut Config) {
+        loop {
+            let command = if self.paused {
+                match self.commands.recv_timeout(PAUSE_POLL) {
+                    Ok(command) => command,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            } else {
+                match self.commands.try_recv() {
+                    Ok(command) => command,
+                    Err(_) => return,
+                }
+            };
+
+            self.handle(command, world, config);
+        }
+    }
+
+    fn handle(&mut self, command: ClientCommand, world: &mut World, config: &mut Config) {
+        match command {
+            ClientCommand::Pause => self.paused = true,
+            ClientCommand::Resume => self.paused = false,
+            ClientCommand::Set { key, value } => {
+                if !World::is_live_parameter(&key) {
+                    self.send_error(format!("'{}' can't be changed while running", key));
+                    return;
+                }
+
+                let mut changed = config.clone();
+                changed.set_parameter(&key, &value);
+                match world.apply_live_parameters(&changed) {
+                    Ok(_) => {
+                        *config = changed;
+                        log::info!("{} set to {}", key, value);
+                    }
+                    Err(e) => {
+                        self.send_error(e);
+                        return;
+                    }
+                }
+            }
+            ClientCommand::DropFood {
+                x,
+                y,
+                radius,
+                amount,
+            } => {
+                let grid_size = world.grid_size();
+                if x >= grid_size.0 || y >= grid_size.1 || radius > grid_size.0.max(grid_size.1) {
+                    self.send_error(format!(
+                        "food at {},{} radius {} is off the grid",
+                        x, y, radius
+                    ));
+                    return;
+                }
+
+                let command = Command::PlaceFood {
+                    pos: (x, y),
+                    radius: radius,
+                    amount: amount,
+                };
+                if let Err(e) = world.apply(&command) {
+                    self.send_error(e);
+                    return;
+                }
+            }
+            ClientCommand::Snapshot => {
+                self.send_world(world);
+                return;
+            }
+        }
+
+        self.send(&Update::Status {
+            tick: world.tick(),
+            paused: self.paused,
+        });
+    }
+
+    /// Sends stats and a frame every `every` ticks
+    pub fn publish(&self, world: &World) {
+        if world.tick() % self.settings.every == 0 {
+            self.send_world(world);
+        }
+    }
+
+    fn send_world(&self, world: &World) {
+        // Nobody would see the frame
+        if self.updates.receiver_count() == 0 {
+            return;
+        }
+
+        self.send(&Update::Stats(&world.stats()));
+
+        let mut frame = Frame::new(self.settings.frame_size, Color::BLACK);
+        draw_world(
+            &mut frame,
+            world,
+            None,
+            &self.viewport,
+            &self.layers,
+            self.background,
+        );
+        match frame.encode_png() {
+            Ok(png) => {
+                let _ = self.updates.send(Message::Binary(png));
+            }
+            Err(e) => {
+                log::error!("server error: {}", &e);
+            }
+        }
+    }
+
+    fn send_error(&self, message: String) {
+        log::error!("server error: {}", &message);
+        self.send(&Update::Error { message: message });
+    }
+
+    fn send(&self, update: &Update) {
+        match serde_json::to_string(update) {
+            // Fails only without clients
+            Ok(text) => {
+                let _ = self.updates.send(Message::Text(text));
+            }
+            Err(e) => {
+                log::error!("server error: {}", &e);
+            }
+        }
+    }
+}
+
+async fn accept(
+    listener: TcpListener,
+    updates: broadcast::Sender<Message>,
+    commands: Sender<ClientCommand>,
+) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                log::info!("client {} connected", peer);
+
+                // Subscribed before the handshake, so no update sent after it gets lost
+                let updates = updates.subscribe();
+                let commands = commands.clone();
+                tokio::spawn(async move {
+                    match connection(stream, updates, commands).await {
+                        Ok(_) => {}
+                        Err(e) => {
+                            log::error!("client {} error: {}", peer, &e);
+                        }
+                    }
+                });
+            }
+            Err(e) => {
+                log::error!("server error: {}", &e);
+            }
+        }
+    }
+}
+
+async fn connection(
+    mut stream: TcpStream,
+    mut updates: broadcast::Receiver<Message>,
+    commands: Sender<ClientCommand>,
+) -> Result<(), String> {
+    if !is_upgrade(&mut stream).await? {
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            PAGE.len(),
+            PAGE
+        );
+        return stream
+            .write_all(response.as_bytes())
+            .await
+            .map_err(|e| e.to_string());
+    }
+
+    let socket = tokio_tungstenite::accept_async(stream)
+        .await
+        .map_err(|e| e.to_string())?;
+    let (mut sink, mut source) = socket.split();
+
+    loop {
+        tokio::select! {
+            message = source.next() => match message {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<ClientCommand>(&text) {
+                    Ok(command) => {
+                        // The run is over
+                        if commands.send(command).is_err() {
+                            return Ok(());
+                        }
+                    }
+                    Err(e) => {
+                        let error = Update::Error { message: format!("invalid command: {}", e) };
+                        let text = serde_json::to_string(&error).map_err(|e| e.to_string())?;
+                        sink.send(Message::Text(text)).await.map_err(|e| e.to_string())?;
+                    }
+                },
+                Some(Ok(Message::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.to_string()),
+            },
+            update = updates.recv() => match update {
+                Ok(message) => sink.send(message).await.map_err(|e| e.to_string())?,
+                // Slow clients skip updates instead of holding up the others
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return Ok(()),
+            },
+        }
+    }
+}
+
+/// Peeks at the request head, plain requests get the page instead of a WebSocket
+async fn is_upgrade(stream: &mut TcpStream) -> Result<bool, String> {
+    let mut head = vec![0u8; MAX_REQUEST_HEAD];
+
+    loop {
+        let len = stream.peek(&mut head).await.map_err(|e| e.to_string())?;
+        if len == 0 {
+            return Err("connection closed".to_string());
+        }
+
+        let text = String::from_utf8_lossy(&head[..len]).to_lowercase();
+        if text.contains("\r\n\r\n") || len == head.len() {
+            let upgrade = text.contains("upgrade: websocket");
+            if !upgrade {
+                // Read what was peeked, closing with unread data resets the connection
+                stream
+                    .read_exact(&mut head[..len])
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            return Ok(upgrade);
+        }
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+    use std::time::Instant;
+    use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+    type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    fn start(world: &World) -> Server {
+        let settings = ServerSettings {
+            addr: "127.0.0.1:0".to_string(),
+            every: 1,
+            frame_size: (40, 40),
+        };
+
+        // The fixture only describes the world
+        let mut config = fixture::config();
+        config.set_parameter("layer_marker_max", "1000");
+        config.set_parameter("layer_food_max", "100");
+        config.set_parameter("background_color", "0,0,0");
+
+        return Server::start(settings, &config, world).unwrap();
+    }
+
+    fn connect(runtime: &Runtime, server: &Server) -> Client {
+        let url = format!("ws://{}", server.local_addr());
+        return runtime.block_on(connect_async(url)).unwrap().0;
+    }
+
+    fn send(runtime: &Runtime, client: &mut Client, text: &str) {
+        runtime
+            .block_on(client.send(Message::Text(text.to_string())))
+            .unwrap();
+    }
+
+    fn receive(runtime: &Runtime, client: &mut Client, timeout: Duration) -> Option<Message> {
+        // Timers need the runtime, so the timeout is made inside it
+        let next = runtime.block_on(async {
+            return tokio::time::timeout(timeout, client.next()).await;
+        });
+        return match next {
+            Ok(message) => Some(message.unwrap().unwrap()),
+            Err(_) => None,
+        };
+    }
+
+    #[test]
+    fn commands_reach_the_world() {
+        let mut world = fixture::world();
+        let mut config = fixture::config();
+        let mut server = start(&world);
+
+        let runtime = Runtime::new().unwrap();
+        let mut client = connect(&runtime, &server);
+        send(&runtime, &mut client, r#"{"cmd": "pause"}"#);
+        send(
+            &runtime,
+            &mut client,
+            r#"{"cmd": "drop_food", "x": 5, "y": 5, "amount": 100}"#,
+        );
+        send(
+            &runtime,
+            &mut client,
+            r#"{"cmd": "set", "key": "ant_energy_drain", "value": "2"}"#,
+        );
+        send(&runtime, &mut client, r#"{"cmd": "resume"}"#);
+
+        // Commands arrive in the background, steer until the last one got through
+        let start = Instant::now();
+        while config.get_parameter("ant_energy_drain").vals[0] != 2.0 {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            server.steer(&mut world, &mut config);
+        }
+
+        assert_eq!(world.stats().food_remaining, 100);
+        assert!(!server.paused);
+    }
+
+    #[test]
+    fn clients_get_stats_frames_and_errors() {
+        let mut world = fixture::world();
+        let mut config = fixture::config();
+        let mut server = start(&world);
+
+        let runtime = Runtime::new().unwrap();
+        let mut client = connect(&runtime, &server);
+
+        server.publish(&world);
+        let stats: serde_json::Value =
+            match receive(&runtime, &mut client, Duration::from_secs(5)).unwrap() {
+                Message::Text(text) => serde_json::from_str(&text).unwrap(),
+                message => panic!("expected stats, got {:?}", message),
+            };
+        assert_eq!(stats["type"], "stats");
+        assert_eq!(stats["num_ants"], 1);
+        match receive(&runtime, &mut client, Duration::from_secs(5)).unwrap() {
+            Message::Binary(png) => assert_eq!(&png[1..4], b"PNG"),
+            message => panic!("expected a frame, got {:?}", message),
+        }
+
+        send(
+            &runtime,
+            &mut client,
+            r#"{"cmd": "set", "key": "grid_dim", "value": "5,5"}"#,
+        );
+        let start = Instant::now();
+        let error = loop {
+            server.steer(&mut world, &mut config);
+            if let Some(Message::Text(text)) =
+                receive(&runtime, &mut client, Duration::from_millis(50))
+            {
+                break serde_json::from_str::<serde_json::Value>(&text).unwrap();
+            }
+            assert!(start.elapsed() < Duration::from_secs(5));
+        };
+        assert_eq!(error["type"], "error");
+        assert_eq!(config.get_parameter("grid_dim").vals, vec![40.0, 40.0]);
+    }
+}
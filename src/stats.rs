@@ -0,0 +1,65 @@
+use serde::Serialize;
+
+use crate::ant::NUM_STATES;
+
+/// Snapshot of the simulation used by the overlay and the metrics recorder
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WorldStats {
+    pub tick: u64,
+
+    pub num_ants: usize,
+    pub ants_per_state: [u32; NUM_STATES],
+    pub births: u64,
+    pub deaths: u64,
+
+    pub food_collected: u64,
+    pub food_stored: u64,
+    pub trips_completed: u64,
+    pub trip_ticks_total: u64,
+    pub food_carried: u64,
+    pub food_remaining: u64,
+    pub food_sources_active: usize,
+    pub food_sources_depleted: usize,
+
+    pub to_home_mass: f64,
+    pub to_food_mass: f64,
+}
+
+/// Stats scenario conditions can test, named like the fields
+pub const STAT_NAMES: [&str; 13] = [
+    "tick",
+    "num_ants",
+    "births",
+    "deaths",
+    "food_collected",
+    "food_stored",
+    "trips_completed",
+    "food_carried",
+    "food_remaining",
+    "food_sources_active",
+    "food_sources_depleted",
+    "to_home_mass",
+    "to_food_mass",
+];
+
+impl WorldStats {
+    /// One of the STAT_NAMES as number
+    pub fn value(&self, name: &str) -> Option<f64> {
+        return match name {
+            "tick" => Some(self.tick as f64),
+            "num_ants" => Some(self.num_ants as f64),
+            "births" => Some(self.births as f64),
+            "deaths" => Some(self.deaths as f64),
+            "food_collected" => Some(self.food_collected as f64),
+            "food_stored" => Some(self.food_stored as f64),
+            "trips_completed" => Some(self.trips_completed as f64),
+            "food_carried" => Some(self.food_carried as f64),
+            "food_remaining" => Some(self.food_remaining as f64),
+            "food_sources_active" => Some(self.food_sources_active as f64),
+            "food_sources_depleted" => Some(self.food_sources_depleted as f64),
+            "to_home_mass" => Some(self.to_home_mass),
+            "to_food_mass" => Some(self.to_food_mass),
+            _ => None,
+        };
+    }
+}
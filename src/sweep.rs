@@ -0,0 +1,284 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    time::Instant,
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    args::{flag_value, flag_values, parse_flag},
+    config::Config,
+    world::World,
+};
+
+/// Config parameter and the values it is swept over
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepParam {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+impl SweepParam {
+    /// Parses "name=start..end step s" (inclusive, step defaults to 1) or "name=a,b,c"
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (name, range) = match spec.find('=') {
+            Some(index) => (spec[..index].trim(), spec[index + 1..].trim()),
+            None => return Err(format!("expected name=range in '{}'", spec)),
+        };
+
+        if !range.contains("..") {
+            return Ok(Self {
+                name: name.to_string(),
+                values: range.split(',').map(|v| v.trim().to_string()).collect(),
+            });
+        }
+
+        let (bounds, step) = match range.find("step") {
+            Some(index) => (range[..index].trim(), range[index + 4..].trim()),
+            None => (range, "1"),
+        };
+        let bounds: Vec<&str> = bounds.split("..").collect();
+
+        let parse = |text: &str| -> Result<f64, String> {
+            return text
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("invalid number '{}' in '{}'", text, spec));
+        };
+        let start = parse(bounds[0])?;
+        let end = parse(bounds[1])?;
+        let step = parse(step)?;
+
+        if step <= 0.0 || end < start {
+            return Err(format!("empty range in '{}'", spec));
+        }
+
+        let mut values: Vec<String> = Vec::new();
+        let mut index = 0;
+        loop {
+            // Multiplying instead of summing keeps float steps from drifting
+            let value = start + step * index as f64;
+            if value > end + step * 1e-9 {
+                break;
+            }
+            values.push(format_value(value));
+            index += 1;
+        }
+
+        return Ok(Self {
+            name: name.to_string(),
+            values: values,
+        });
+    }
+}
+
+fn format_value(value: f64) -> String {
+    if value.fract() == 0.0 {
+        return format!("{}", value as i64);
+    }
+    return format!("{}", value);
+}
+
+/// Every combination of the parameter values, in the order the parameters were given
+pub fn combinations(params: &[SweepParam]) -> Vec<Vec<String>> {
+    let mut combinations: Vec<Vec<String>> = vec![Vec::new()];
+
+    for param in params.iter() {
+        let mut extended: Vec<Vec<String>> = Vec::new();
+        for combination in combinations.iter() {
+            for value in param.values.iter() {
+                let mut next = combination.clone();
+                next.push(value.clone());
+                extended.push(next);
+            }
+        }
+        combinations = extended;
+    }
+
+    return combinations;
+}
+
+struct RunSummary {
+    values: Vec<String>,
+    seed: u64,
+    food_collected: u64,
+    population: usize,
+    first_delivery: Option<u64>,
+    food_remaining: u64,
+    trips_completed: u64,
+    mean_trip_length: f64,
+}
+
+fn run_once(config: &Config, ticks: u64) -> (World, Option<u64>) {
+    let mut world = World::from_config(config);
+    let mut first_delivery: Option<u64> = None;
+
+    for _ in 0..ticks {
+        world.update();
+
+        if first_delivery.is_none() && world.food_collected() > 0 {
+            first_delivery = Some(world.tick());
+        }
+    }
+
+    return (world, first_delivery);
+}
+
+/// Runs headless simulations for every parameter combination and seed in parallel:
+/// `ants sweep --param "num_ants=500..5000 step 500" --seeds 1..4 --ticks 3000 --out sweep.csv`
+pub fn run(config: &Config, args: &[String]) {
+    let mut params: Vec<SweepParam> = Vec::new();
+    for spec in flag_values(args, "--param") {
+        match SweepParam::parse(spec) {
+            Ok(param) => params.push(param),
+            Err(e) => {
+                log::error!("sweep error: {}", &e);
+                std::process::exit(-1);
+            }
+        }
+    }
+
+    let seeds = match SweepParam::parse(&format!(
+        "seed={}",
+        flag_value(args, "--seeds").unwrap_or("1")
+    )) {
+        Ok(seeds) => seeds.values,
+        Err(e) => {
+            log::error!("sweep error: {}", &e);
+            std::process::exit(-1);
+        }
+    };
+
+    let ticks: u64 = parse_flag(args, "--ticks", 3000);
+    let out_path = flag_value(args, "--out").unwrap_or("sweep.csv");
+
+    // Seeds vary fastest, so runs of the same combination end up next to each other
+    let mut runs: Vec<(Vec<String>, String)> = Vec::new();
+    for combination in combinations(&params) {
+        for seed in seeds.iter() {
+            runs.push((combination.clone(), seed.clone()));
+        }
+    }
+
+    println!("sweeping {} runs of {} ticks", runs.len(), ticks);
+    let start = Instant::now();
+
+    let summaries: Vec<RunSummary> = runs
+        .par_iter()
+        .map(|(values, seed)| {
+            let mut run_config = config.clone();
+            for (param, value) in params.iter().zip(values.iter()) {
+                run_config.set_parameter(&param.name, value);
+            }
+            run_config.set_parameter("seed", seed);
+
+            let (world, first_delivery) = run_once(&run_config, ticks);
+            let stats = world.stats();
+
+            return RunSummary {
+                values: values.clone(),
+                seed: world.seed(),
+                food_collected: stats.food_collected,
+                population: stats.num_ants,
+                first_delivery: first_delivery,
+                food_remaining: stats.food_remaining,
+                trips_completed: stats.trips_completed,
+                mean_trip_length: if stats.trips_completed > 0 {
+                    stats.trip_ticks_total as f64 / stats.trips_completed as f64
+                } else {
+                    0.0
+                },
+            };
+        })
+        .collect();
+
+    match write_summaries(out_path, &params, ticks, &summaries) {
+        Ok(_) => {}
+        Err(e) => {
+            log::error!("sweep error: {}", &e);
+            std::process::exit(-1);
+        }
+    }
+
+    println!(
+        "{} runs in {:.2}s, written to {}",
+        summaries.len(),
+        start.elapsed().as_secs_f64(),
+        out_path
+    );
+}
+
+fn write_summaries(
+    path: &str,
+    params: &[SweepParam],
+    ticks: u64,
+    summaries: &[RunSummary],
+) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut writer = BufWriter::new(file);
+
+    let mut header: Vec<String> = params.iter().map(|param| param.name.clone()).collect();
+    header.push("seed".to_string());
+    header.push("ticks".to_string());
+    header.push("food_collected".to_string());
+    header.push("first_delivery_tick".to_string());
+    header.push("population".to_string());
+    header.push("food_remaining".to_string());
+    header.push("trips".to_string());
+    header.push("mean_trip_length".to_string());
+    writeln!(writer, "{}", header.join(",")).map_err(|e| e.to_string())?;
+
+    for summary in summaries.iter() {
+        let mut fields = summary.values.clone();
+        fields.push(summary.seed.to_string());
+        fields.push(ticks.to_string());
+        fields.push(summary.food_collected.to_string());
+        fields.push(match summary.first_delivery {
+            Some(tick) => tick.to_string(),
+            None => "".to_string(),
+        });
+        fields.push(summary.population.to_string());
+        fields.push(summary.food_remaining.to_string());
+        fields.push(summary.trips_completed.to_string());
+        fields.push(format!("{:.2}", summary.mean_trip_length));
+
+        writeln!(writer, "{}", fields.join(",")).map_err(|e| e.to_string())?;
+    }
+
+    return writer.flush().map_err(|e| e.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_include_the_end_and_lists_keep_their_order() {
+        let param = SweepParam::parse("num_ants=500..2000 step 500").unwrap();
+        assert_eq!(param.name, "num_ants");
+        assert_eq!(param.values, vec!["500", "1000", "1500", "2000"]);
+
+        let param = SweepParam::parse("food_regrowth_rate=0..0.3 step 0.1").unwrap();
+        assert_eq!(param.values.len(), 4);
+
+        let param = SweepParam::parse("food_layout=ring,uniform").unwrap();
+        assert_eq!(param.values, vec!["ring", "uniform"]);
+
+        assert!(SweepParam::parse("num_ants").is_err());
+        assert!(SweepParam::parse("num_ants=10..1").is_err());
+    }
+
+    #[test]
+    fn combinations_cross_all_params() {
+        let params = vec![
+            SweepParam::parse("a=1..2").unwrap(),
+            SweepParam::parse("b=x,y,z").unwrap(),
+        ];
+
+        let combinations = combinations(&params);
+        assert_eq!(combinations.len(), 6);
+        assert_eq!(combinations[0], vec!["1", "x"]);
+        assert_eq!(combinations[5], vec!["2", "z"]);
+    }
+}
@@ -1,22 +1,33 @@
-use sdl2::pixels::Color;
-
 use crate::{food::Food, marker::Marker};
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Terrain {
+    Ground,
+    Wall,
+}
+
 pub struct Tile {
     pub markers: (Marker, Marker),
     pub food: Food,
+    pub terrain: Terrain,
 }
 
 #[allow(dead_code)]
 impl Tile {
-    pub fn get_color(&self) -> Color {
-        return Color::RGBA(
-            self.markers.0.m_type * 100,
-            self.food.concentration.clamp(0, 255) as u8,
-            self.markers.1.m_type * 100,
-            (self.markers.0.strength + self.markers.1.strength + self.food.concentration as f64)
-                .clamp(0.0, 255.0) as u8,
-        );
+    /// Strength of the trail laid by ants leaving the nest, zero if none was dropped
+    pub fn to_home_strength(&self) -> f64 {
+        if self.markers.0.m_type == 0 {
+            return 0.0;
+        }
+        return self.markers.0.strength;
+    }
+
+    /// Strength of the trail laid by ants carrying food, zero if none was dropped
+    pub fn to_food_strength(&self) -> f64 {
+        if self.markers.1.m_type == 0 {
+            return 0.0;
+        }
+        return self.markers.1.strength;
     }
 
     pub fn update(&mut self) {
@@ -24,12 +35,40 @@ impl Tile {
         self.markers.1.update();
     }
 
-    /// Returns false if concentration minus one is negative
+    pub fn fade_markers(&mut self, amount: f64) {
+        self.markers.0.fade(amount);
+        self.markers.1.fade(amount);
+    }
+
+    /// Returns false if there is no food left
     pub fn sub_food(&mut self) -> bool {
-        if self.food.concentration as i32 - 1 < 0 {
+        if self.food.concentration == 0 {
             return false;
         }
         self.food.concentration -= 1;
         return true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sub_food_never_underflows() {
+        let mut tile = Tile {
+            markers: (Marker::new(0), Marker::new(0)),
+            food: Food {
+                concentration: 2,
+                source: None,
+            },
+            terrain: Terrain::Ground,
+        };
+
+        assert!(tile.sub_food());
+        assert!(tile.sub_food());
+        assert!(!tile.sub_food());
+        assert!(!tile.sub_food());
+        assert_eq!(tile.food.concentration, 0);
+    }
+}
@@ -0,0 +1,77 @@
+use sdl2::{
+    pixels::PixelFormatEnum,
+    rect::Rect,
+    render::{BlendMode, Canvas, Texture, TextureCreator},
+    video::{Window, WindowContext},
+};
+
+use crate::{grid::DirtyRegion, renderer::Heatmap};
+
+// ABGR8888 is laid out as R, G, B, A bytes in memory on little endian machines
+const PIXEL_FORMAT: PixelFormatEnum = PixelFormatEnum::ABGR8888;
+const BYTES_PER_PIXEL: usize = 4;
+
+/// Streaming texture holding one pixel per tile, drawn with a single scaled copy
+pub struct TileTexture<'a> {
+    texture: Texture<'a>,
+    size: (u32, u32),
+}
+
+impl<'a> TileTexture<'a> {
+    pub fn new(
+        texture_creator: &'a TextureCreator<WindowContext>,
+        grid_size: (u32, u32),
+    ) -> Result<Self, String> {
+        let mut texture = texture_creator
+            .create_texture_streaming(PIXEL_FORMAT, grid_size.0, grid_size.1)
+            .map_err(|e| e.to_string())?;
+
+        texture.set_blend_mode(BlendMode::Blend);
+
+        return Ok(Self {
+            texture: texture,
+            size: grid_size,
+        });
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        return self.size;
+    }
+
+    /// Uploads the tiles inside the region, everything else keeps its previous pixels
+    pub fn update(&mut self, heatmap: &Heatmap, region: DirtyRegion) {
+        let result = self.texture.with_lock(
+            Rect::new(
+                region.min.0 as i32,
+                region.min.1 as i32,
+                region.width(),
+                region.height(),
+            ),
+            |buffer: &mut [u8], pitch: usize| {
+                for y in 0..region.height() as usize {
+                    for x in 0..region.width() as usize {
+                        let color =
+                            heatmap.color((region.min.0 + x as u32, region.min.1 + y as u32));
+
+                        let offset = y * pitch + x * BYTES_PER_PIXEL;
+                        buffer[offset] = color.r;
+                        buffer[offset + 1] = color.g;
+                        buffer[offset + 2] = color.b;
+                        buffer[offset + 3] = color.a;
+                    }
+                }
+            },
+        );
+
+        match result {
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("texture update error: {}", &e);
+            }
+        }
+    }
+
+    pub fn render(&self, canvas: &mut Canvas<Window>, dest: Rect) -> Result<(), String> {
+        return canvas.copy(&self.texture, None, dest);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::command::Command;
+
+const MAX_RADIUS: u32 = 30;
+const MAX_FOOD_AMOUNT: u32 = 64000;
+const MIN_FOOD_AMOUNT: u32 = 125;
+const MARKER_PAINT_STRENGTH: f64 = 500.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tool {
+    Inspect,
+    FoodBrush,
+    WallBrush,
+    Eraser,
+    PheromonePaint,
+    PheromoneErase,
+    MoveHill,
+}
+
+impl Tool {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tool::Inspect => "inspect",
+            Tool::FoodBrush => "food brush",
+            Tool::WallBrush => "wall brush",
+            Tool::Eraser => "eraser",
+            Tool::PheromonePaint => "pheromone paint",
+            Tool::PheromoneErase => "pheromone erase",
+            Tool::MoveHill => "move hill",
+        }
+    }
+}
+
+/// Active mouse tool and its brush settings
+pub struct ToolPalette {
+    active: Tool,
+    radius: u32,
+    food_amount: u32,
+    marker_type: u8,
+
+    // Last tile the tool was applied to, so dragging doesn't reapply on the same tile
+    last_applied: Option<(u32, u32)>,
+}
+
+impl ToolPalette {
+    pub fn new() -> Self {
+        return Self {
+            active: Tool::FoodBrush,
+            radius: 0,
+            food_amount: 2000,
+            marker_type: 2,
+
+            last_applied: None,
+        };
+    }
+
+    pub fn active(&self) -> Tool {
+        return self.active;
+    }
+
+    /// Selecting the pheromone painter again switches between the two trails
+    pub fn select(&mut self, tool: Tool) {
+        if tool == Tool::PheromonePaint && self.active == Tool::PheromonePaint {
+            self.marker_type = if self.marker_type == 1 { 2 } else { 1 };
+        }
+        self.active = tool;
+    }
+
+    pub fn adjust_radius(&mut self, delta: i32) {
+        self.radius = (self.radius as i32 + delta).clamp(0, MAX_RADIUS as i32) as u32;
+    }
+
+    pub fn double_amount(&mut self) {
+        self.food_amount = (self.food_amount * 2).min(MAX_FOOD_AMOUNT);
+    }
+
+    pub fn halve_amount(&mut self) {
+        self.food_amount = (self.food_amount / 2).max(MIN_FOOD_AMOUNT);
+    }
+
+    /// Starts a new stroke, must be called when the mouse button goes down
+    pub fn begin_stroke(&mut self) {
+        self.last_applied = None;
+    }
+
+    /// Command for applying the active tool at the tile, None if nothing should happen
+    pub fn command_at(&mut self, pos: (u32, u32)) -> Option<Command> {
+        if self.last_applied == Some(pos) {
+            return None;
+        }
+        self.last_applied = Some(pos);
+
+        let radius = self.radius;
+
+        match self.active {
+            Tool::Inspect => {
+                return None;
+            }
+            Tool::FoodBrush => {
+                return Some(Command::PlaceFood {
+                    pos: pos,
+                    radius: radius,
+                    amount: self.food_amount,
+                });
+            }
+            Tool::WallBrush => {
+                return Some(Command::PlaceWall {
+                    pos: pos,
+                    radius: radius,
+                });
+            }
+            Tool::Eraser => {
+                return Some(Command::Erase {
+                    pos: pos,
+                    radius: radius,
+                });
+            }
+            Tool::PheromonePaint => {
+                return Some(Command::PaintMarker {
+                    pos: pos,
+                    radius: radius,
+                    m_type: self.marker_type,
+                    strength: MARKER_PAINT_STRENGTH,
+                });
+            }
+            Tool::PheromoneErase => {
+                return Some(Command::EraseMarkers {
+                    pos: pos,
+                    radius: radius,
+                });
+            }
+            Tool::MoveHill => {
+                return Some(Command::MoveHill { pos: pos });
+            }
+        }
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let tools = [
+            (Tool::Inspect, "I"),
+            (Tool::FoodBrush, "F"),
+            (Tool::WallBrush, "W"),
+            (Tool::Eraser, "E"),
+            (Tool::PheromonePaint, "P"),
+            (Tool::PheromoneErase, "X"),
+            (Tool::MoveHill, "M"),
+        ];
+
+        let mut lines: Vec<String> = Vec::new();
+
+        for (tool, key) in tools.iter() {
+            let marker = if *tool == self.active { ">" } else { " " };
+            lines.push(format!("{} [{}] {}", marker, key, tool.name()));
+        }
+
+        lines.push(format!("radius {}  (wheel)", self.radius));
+        lines.push(format!("food amount {}  ([ ])", self.food_amount));
+        lines.push(format!(
+            "paint trail {}",
+            if self.marker_type == 1 {
+                "to-home"
+            } else {
+                "to-food"
+            }
+        ));
+
+        return lines;
+    }
+}
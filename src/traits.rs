@@ -0,0 +1,304 @@
+use rand::{rngs::StdRng, Rng};
+
+use crate::{config::Config, util::sample_normal};
+
+/// Distribution a trait is drawn from, written in config as
+/// "fixed,v", "uniform,min,max" or "normal,mean,std_dev"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TraitDist {
+    Fixed(f64),
+    Uniform(f64, f64),
+    Normal(f64, f64),
+}
+
+impl TraitDist {
+    pub fn parse(words: &[String]) -> Result<Self, String> {
+        let numbers: Vec<f64> = words[1..]
+            .iter()
+            .map(|word| word.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| format!("invalid number in '{}'", words.join(",")))?;
+
+        return match (words[0].as_str(), numbers.len()) {
+            ("fixed", 1) => Ok(TraitDist::Fixed(numbers[0])),
+            ("uniform", 2) if numbers[0] <= numbers[1] => {
+                Ok(TraitDist::Uniform(numbers[0], numbers[1]))
+            }
+            ("normal", 2) => Ok(TraitDist::Normal(numbers[0], numbers[1].abs())),
+            _ => Err(format!("invalid distribution '{}'", words.join(","))),
+        };
+    }
+
+    /// Traits are never negative, normal samples below zero are clamped
+    pub fn sample(&self, rng: &mut StdRng) -> f64 {
+        let value = match *self {
+            TraitDist::Fixed(value) => value,
+            TraitDist::Uniform(min, max) => {
+                if min == max {
+                    min
+                } else {
+                    rng.gen_range(min..max)
+                }
+            }
+            TraitDist::Normal(mean, std_dev) => sample_normal(rng, mean, std_dev),
+        };
+
+        return value.max(0.0);
+    }
+}
+
+pub const TRAIT_NAMES: [&str; 8] = [
+    "speed",
+    "size",
+    "perception_radius",
+    "pheromone_radius",
+    "act_perception_radius",
+    "wander_sway",
+    "marker_strength",
+    "marker_drop_rate",
+];
+
+/// Config keys of trait distributions: "ant_<trait>" and "caste_<name>_<trait>".
+/// The caste list and the shares are not among them, they shape the colony.
+pub fn is_trait_parameter(name: &str) -> bool {
+    return TRAIT_NAMES.iter().any(|trait_name| {
+        return name == format!("ant_{}", trait_name)
+            || (name.starts_with("caste_") && name.ends_with(&format!("_{}", trait_name)));
+    });
+}
+
+/// Distributions for every trait of an ant, radii and rates are truncated to whole numbers
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraitProfile {
+    pub speed: TraitDist,
+    pub size: TraitDist,
+    pub perception_radius: TraitDist,
+    pub pheromone_radius: TraitDist,
+    pub act_perception_radius: TraitDist,
+    pub wander_sway: TraitDist,
+    pub marker_strength: TraitDist,
+    pub marker_drop_rate: TraitDist,
+}
+
+impl TraitProfile {
+    /// Name has to be one of the TRAIT_NAMES
+    pub fn get(&self, name: &str) -> TraitDist {
+        return match name {
+            "speed" => self.speed,
+            "size" => self.size,
+            "perception_radius" => self.perception_radius,
+            "pheromone_radius" => self.pheromone_radius,
+            "act_perception_radius" => self.act_perception_radius,
+            "wander_sway" => self.wander_sway,
+            "marker_strength" => self.marker_strength,
+            "marker_drop_rate" => self.marker_drop_rate,
+            _ => unreachable!("unknown trait '{}'", name),
+        };
+    }
+
+    fn get_mut(&mut self, name: &str) -> &mut TraitDist {
+        match name {
+            "speed" => &mut self.speed,
+            "size" => &mut self.size,
+            "perception_radius" => &mut self.perception_radius,
+            "pheromone_radius" => &mut self.pheromone_radius,
+            "act_perception_radius" => &mut self.act_perception_radius,
+            "wander_sway" => &mut self.wander_sway,
+            "marker_strength" => &mut self.marker_strength,
+            "marker_drop_rate" => &mut self.marker_drop_rate,
+            _ => unreachable!("unknown trait '{}'", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Caste {
+    pub name: String,
+    /// Relative share of spawned ants
+    pub share: f64,
+    pub traits: TraitProfile,
+}
+
+/// Castes of a colony. Traits default to the "ant_<trait>" keys, a caste can override
+/// any of them with "caste_<name>_<trait>".
+#[derive(Debug, Clone, PartialEq)]
+pub struct AntTraits {
+    pub castes: Vec<Caste>,
+}
+
+impl AntTraits {
+    pub fn from_config(config: &Config) -> Self {
+        return match AntTraits::parse(config) {
+            Ok(traits) => traits,
+            Err(e) => {
+                log::error!("{}", &e);
+                std::process::exit(-1);
+            }
+        };
+    }
+
+    /// Like from_config, but a broken distribution is returned as error
+    pub fn parse(config: &Config) -> Result<Self, String> {
+        let mut caste_names: Vec<String> = Vec::new();
+        if config.has_parameter("ant_castes") {
+            caste_names = config.get_words("ant_castes");
+            caste_names.retain(|name| !name.is_empty());
+        }
+        if caste_names.is_empty() {
+            return Err("ant_castes needs at least one caste".to_string());
+        }
+
+        let mut castes: Vec<Caste> = Vec::new();
+        for caste_name in caste_names {
+            castes.push(Caste {
+                share: config.get_values(&format!("caste_{}_share", caste_name), 1)?[0],
+                traits: AntTraits::parse_profile(config, &caste_name)?,
+                name: caste_name,
+            });
+        }
+
+        return Ok(Self { castes: castes });
+    }
+
+    /// The same castes with their distributions read again from the config,
+    /// changes to the caste list or the shares are left out
+    pub fn reparse_profiles(&self, config: &Config) -> Result<Self, String> {
+        let mut castes: Vec<Caste> = Vec::new();
+
+        for caste in self.castes.iter() {
+            castes.push(Caste {
+                name: caste.name.clone(),
+                share: caste.share,
+                traits: AntTraits::parse_profile(config, &caste.name)?,
+            });
+        }
+
+        return Ok(Self { castes: castes });
+    }
+
+    // "ant_<trait>" for every trait, overridden by "caste_<name>_<trait>" where given
+    fn parse_profile(config: &Config, caste_name: &str) -> Result<TraitProfile, String> {
+        let parse = |key: &str| -> Result<TraitDist, String> {
+            if !config.has_parameter(key) {
+                return Err(format!("parameter '{}' not found", key));
+            }
+            return TraitDist::parse(&config.get_words(key)).map_err(|e| format!("{}: {}", key, e));
+        };
+
+        let mut traits = TraitProfile {
+            speed: TraitDist::Fixed(0.0),
+            size: TraitDist::Fixed(0.0),
+            perception_radius: TraitDist::Fixed(0.0),
+            pheromone_radius: TraitDist::Fixed(0.0),
+            act_perception_radius: TraitDist::Fixed(0.0),
+            wander_sway: TraitDist::Fixed(0.0),
+            marker_strength: TraitDist::Fixed(0.0),
+            marker_drop_rate: TraitDist::Fixed(0.0),
+        };
+        for name in TRAIT_NAMES.iter() {
+            let key = format!("caste_{}_{}", caste_name, name);
+            *traits.get_mut(name) = if config.has_parameter(&key) {
+                parse(&key)?
+            } else {
+                parse(&format!("ant_{}", name))?
+            };
+        }
+
+        return Ok(traits);
+    }
+
+    /// Picks a caste weighted by share
+    pub fn choose_caste(&self, rng: &mut StdRng) -> usize {
+        let total: f64 = self.castes.iter().map(|caste| caste.share).sum();
+        if total <= 0.0 {
+            return 0;
+        }
+
+        let mut pick = rng.gen_range(0.0..total);
+        for (index, caste) in self.castes.iter().enumerate() {
+            if pick < caste.share {
+                return index;
+            }
+            pick -= caste.share;
+        }
+
+        return self.castes.len() - 1;
+    }
+
+    pub fn caste_name(&self, caste: usize) -> &str {
+        return match self.castes.get(caste) {
+            Some(caste) => &caste.name,
+            None => "unknown",
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+    use rand::SeedableRng;
+
+    fn words(text: &str) -> Vec<String> {
+        return text.split(',').map(|word| word.to_string()).collect();
+    }
+
+    #[test]
+    fn distributions_parse_and_stay_in_range() {
+        let mut rng = StdRng::seed_from_u64(3);
+
+        assert_eq!(
+            TraitDist::parse(&words("fixed,2.5")),
+            Ok(TraitDist::Fixed(2.5))
+        );
+        assert!(TraitDist::parse(&words("uniform,4,1")).is_err());
+        assert!(TraitDist::parse(&words("normal,1")).is_err());
+        assert!(TraitDist::parse(&words("gamma,1,2")).is_err());
+
+        let uniform = TraitDist::parse(&words("uniform,3,6")).unwrap();
+        let normal = TraitDist::parse(&words("normal,0.5,2")).unwrap();
+        for _ in 0..1000 {
+            let value = uniform.sample(&mut rng);
+            assert!(value >= 3.0 && value < 6.0);
+            assert!(normal.sample(&mut rng) >= 0.0);
+        }
+    }
+
+    #[test]
+    fn castes_override_traits_and_are_picked_by_share() {
+        let mut config = fixture::config();
+        config.set_parameter("ant_castes", "worker,soldier");
+        config.set_parameter("caste_worker_share", "3");
+        config.set_parameter("caste_soldier_share", "1");
+        config.set_parameter("caste_soldier_size", "fixed,6");
+
+        let traits = AntTraits::parse(&config).unwrap();
+        let (worker, soldier) = (&traits.castes[0].traits, &traits.castes[1].traits);
+        assert_eq!(worker.size, TraitDist::Fixed(3.0));
+        assert_eq!(soldier.size, TraitDist::Fixed(6.0));
+        assert_eq!(soldier.speed, worker.speed);
+
+        let mut rng = StdRng::seed_from_u64(5);
+        let soldiers = (0..4000)
+            .filter(|_| traits.choose_caste(&mut rng) == 1)
+            .count();
+        assert!(soldiers > 850 && soldiers < 1150, "{} soldiers", soldiers);
+
+        config.set_parameter("ant_castes", "worker,scout");
+        assert!(AntTraits::parse(&config).is_err());
+    }
+
+    #[test]
+    fn a_colony_needs_a_caste() {
+        let mut config = fixture::config();
+
+        config.set_parameter("ant_castes", ",");
+        assert_eq!(
+            AntTraits::parse(&config),
+            Err("ant_castes needs at least one caste".to_string())
+        );
+
+        config.parameters.remove("ant_castes");
+        assert!(AntTraits::parse(&config).is_err());
+    }
+}
@@ -0,0 +1,422 @@
+use std::{
+    io::{stdout, Stdout, Write},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    queue, style, terminal,
+};
+use sdl2::{pixels::Color, rect::Rect};
+
+use crate::{
+    config::Config,
+    draw::{draw_ant_hill, draw_tiles, draw_world},
+    frame::Frame,
+    layers::{LayerSettings, RenderLayer},
+    overlay::Overlay,
+    pacing::Pacing,
+    renderer::Renderer,
+    scenario::Scenario,
+    viewport::Viewport,
+    world::World,
+};
+
+// Columns kept free for the stats panel on the right
+const PANEL_WIDTH: u16 = 32;
+
+// Ants on one pixel until it shows the full ant color
+const FULL_DENSITY: f64 = 4.0;
+
+const FRAME_TIME: Duration = Duration::from_millis(1000 / 30);
+
+/// One character cell, the upper half block is drawn in the top color over the bottom one
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cell {
+    top: Color,
+    bottom: Color,
+}
+
+/// Terminal front-end for machines without a display: `ants tui`.
+/// Every character cell shows two pixels of the world with a half block in 24-bit color,
+/// the keys are the same as in the window. Log output goes to stderr, redirect it
+/// to keep it out of the picture.
+pub fn run(config: &Config, scenario: Option<Scenario>) {
+    let mut out = stdout();
+
+    let result = terminal::enable_raw_mode()
+        .and_then(|_| queue!(out, terminal::EnterAlternateScreen, cursor::Hide))
+        .and_then(|_| out.flush())
+        .and_then(|_| run_loop(config, scenario, &mut out));
+
+    // The terminal is handed back even if drawing failed
+    let restored = queue!(
+        out,
+        style::ResetColor,
+        cursor::Show,
+        terminal::LeaveAlternateScreen
+    )
+    .and_then(|_| out.flush())
+    .and_then(|_| terminal::disable_raw_mode());
+
+    for result in [result, restored] {
+        match result {
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("terminal error: {}", &e);
+            }
+        }
+    }
+}
+
+fn run_loop(
+    config: &Config,
+    mut scenario: Option<Scenario>,
+    out: &mut Stdout,
+) -> std::io::Result<()> {
+    let mut world = World::from_config(config);
+
+    let background_color = Color::RGB(
+        config.get_parameter("background_color").vals[0] as u8,
+        config.get_parameter("background_color").vals[1] as u8,
+        config.get_parameter("background_color").vals[2] as u8,
+    );
+    let mut layers = LayerSettings::new(
+        config.get_parameter("layer_marker_max").vals[0],
+        config.get_parameter("layer_food_max").vals[0],
+    );
+    let mut overlay = Overlay::new(true);
+    let mut pacing = Pacing::new();
+
+    // Cells currently on screen, emptied whenever the whole screen has to be redrawn
+    let mut screen: Vec<Cell> = Vec::new();
+    let mut terminal_size = terminal::size()?;
+
+    // Result of the last S press, shown in the panel
+    let mut status = String::new();
+
+    let mut fps_clock = Instant::now();
+    let mut frames_since_fps = 0;
+    let mut fps = 0;
+
+    'running: loop {
+        let frame_start = Instant::now();
+
+        // ! Input --
+        while event::poll(Duration::ZERO)? {
+            match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => break 'running,
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        break 'running
+                    }
+                    KeyCode::Char(digit @ '1'..='5') => {
+                        layers.set_active(RenderLayer::ALL[digit as usize - '1' as usize]);
+                    }
+                    KeyCode::Char('c') => layers.cycle_color_map(),
+                    KeyCode::Char('l') => layers.toggle_scaling(),
+                    KeyCode::Tab => {
+                        overlay.toggle();
+                        screen.clear();
+                    }
+                    KeyCode::Char('s') => status = save_frame(config, &world, &layers),
+                    KeyCode::Char(' ') => {
+                        pacing.toggle_pause();
+                    }
+                    KeyCode::Char('=') | KeyCode::Char('+') => pacing.faster(),
+                    KeyCode::Char('-') => pacing.slower(),
+                    _ => {}
+                },
+                Event::Resize(w, h) => {
+                    terminal_size = (w, h);
+                    screen.clear();
+                }
+                _ => {}
+            }
+        }
+
+        // ! Update --
+        for _ in 0..pacing.take_step() {
+            if let Some(scenario) = scenario.as_mut() {
+                for command in scenario.due(&world) {
+                    match world.apply(&command) {
+                        Ok(_) => {}
+                        Err(e) => {
+                            log::error!("scenario error: {}", &e);
+                        }
+                    }
+                }
+            }
+
+            world.update();
+            world.drain_food_events();
+        }
+
+        // * Render --
+        let panel_width = if overlay.is_visible() { PANEL_WIDTH } else { 0 };
+        let columns = terminal_size.0.saturating_sub(panel_width).max(1);
+        let rows = terminal_size.1.max(1);
+
+        let frame = draw_frame(
+            &world,
+            &layers,
+            background_color,
+            (columns as u32, rows as u32 * 2),
+        );
+        let cells = to_cells(&frame);
+
+        if screen.len() != cells.len() {
+            queue!(
+                out,
+                style::ResetColor,
+                terminal::Clear(terminal::ClearType::All)
+            )?;
+        }
+        draw_cells(out, &cells, &screen, columns)?;
+        screen = cells;
+
+        if overlay.is_visible() {
+            let mut lines = Overlay::stat_lines(&world.stats(), fps);
+            lines.push(String::new());
+            lines.push(pacing.label());
+            lines.push(format!(
+                "layer {} ({}, {})",
+                layers.active().name(),
+                layers.active_style().color_map.name(),
+                layers.active_style().scaling.name()
+            ));
+            lines.push(status.clone());
+
+            draw_panel(out, &lines, columns, terminal_size)?;
+        }
+
+        out.flush()?;
+        // * Render end --
+
+        frames_since_fps += 1;
+        if fps_clock.elapsed().as_millis() >= 1000 {
+            fps = frames_since_fps;
+            frames_since_fps = 0;
+            fps_clock = Instant::now();
+        }
+
+        // Delay
+        if let Some(rest) = FRAME_TIME.checked_sub(frame_start.elapsed()) {
+            std::thread::sleep(rest);
+        }
+    }
+
+    return Ok(());
+}
+
+/// The world downsampled to size, with ants drawn as density instead of single rects
+fn draw_frame(world: &World, layers: &LayerSettings, background: Color, size: (u32, u32)) -> Frame {
+    let mut frame = Frame::new(size, Color::BLACK);
+    let viewport = Viewport::new(world.world_size(), size);
+
+    draw_tiles(&mut frame, world, None, &viewport, layers, background);
+
+    // Several ants share a pixel at this size, more of them show more of the ant color
+    let mut density = vec![0u32; size.0 as usize * size.1 as usize];
+    for ant in world.ants() {
+        let pos = viewport.world_to_screen(ant.pos());
+        if pos.x >= 0.0 && pos.y >= 0.0 && pos.x < size.0 as f64 && pos.y < size.1 as f64 {
+            density[pos.y as usize * size.0 as usize + pos.x as usize] += 1;
+        }
+    }
+
+    let ant_color = world.ant_color();
+    for (index, count) in density.iter().enumerate() {
+        if *count == 0 {
+            continue;
+        }
+
+        let alpha = (*count as f64 / FULL_DENSITY).min(1.0) * 255.0;
+        frame.fill_rects(
+            &[Rect::new(
+                (index % size.0 as usize) as i32,
+                (index / size.0 as usize) as i32,
+                1,
+                1,
+            )],
+            Color::RGBA(ant_color.r, ant_color.g, ant_color.b, alpha as u8),
+        );
+    }
+
+    draw_ant_hill(&mut frame, world.ant_hill(), &viewport);
+
+    return frame;
+}
+
+/// Pairs of pixel rows as cells, row by row
+fn to_cells(frame: &Frame) -> Vec<Cell> {
+    let (width, height) = frame.size();
+    let mut cells: Vec<Cell> = Vec::with_capacity((width * height / 2) as usize);
+
+    for row in 0..height / 2 {
+        for x in 0..width {
+            cells.push(Cell {
+                top: frame.pixel(x, row * 2),
+                bottom: frame.pixel(x, row * 2 + 1),
+            });
+        }
+    }
+
+    return cells;
+}
+
+fn term_color(color: Color) -> style::Color {
+    return style::Color::Rgb {
+        r: color.r,
+        g: color.g,
+        b: color.b,
+    };
+}
+
+/// Only writes cells that differ from the ones on screen, the rest of the terminal is kept
+fn draw_cells(
+    out: &mut Stdout,
+    cells: &[Cell],
+    screen: &[Cell],
+    columns: u16,
+) -> std::io::Result<()> {
+    // Cursor and colors left by the last write, so runs of cells skip repeating them
+    let mut cursor_at: Option<usize> = None;
+    let mut colors: Option<Cell> = None;
+
+    for (index, cell) in cells.iter().enumerate() {
+        if screen.get(index) == Some(cell) {
+            continue;
+        }
+
+        if cursor_at != Some(index) || index % columns as usize == 0 {
+            queue!(
+                out,
+                cursor::MoveTo(
+                    (index % columns as usize) as u16,
+                    (index / columns as usize) as u16
+                )
+            )?;
+        }
+        if colors != Some(*cell) {
+            queue!(
+                out,
+                style::SetColors(style::Colors::new(
+                    term_color(cell.top),
+                    term_color(cell.bottom)
+                ))
+            )?;
+            colors = Some(*cell);
+        }
+
+        queue!(out, style::Print('▀'))?;
+        cursor_at = Some(index + 1);
+    }
+
+    return Ok(());
+}
+
+fn draw_panel(
+    out: &mut Stdout,
+    lines: &[String],
+    column: u16,
+    terminal_size: (u16, u16),
+) -> std::io::Result<()> {
+    queue!(out, style::ResetColor)?;
+
+    let width = terminal_size.0.saturating_sub(column + 1) as usize;
+    for (row, line) in lines.iter().take(terminal_size.1 as usize).enumerate() {
+        // Padded so shorter lines overwrite what the last frame wrote there
+        let text: String = line.chars().take(width).collect();
+        queue!(
+            out,
+            cursor::MoveTo(column + 1, row as u16),
+            style::Print(format!("{:width$}", text, width = width))
+        )?;
+    }
+
+    return Ok(());
+}
+
+/// Full size picture like the window's, the terminal one is too coarse to be useful
+fn save_frame(config: &Config, world: &World, layers: &LayerSettings) -> String {
+    let size = (
+        config.get_parameter("win_size").vals[0] as u32,
+        config.get_parameter("win_size").vals[1] as u32,
+    );
+    let background = Color::RGB(
+        config.get_parameter("background_color").vals[0] as u8,
+        config.get_parameter("background_color").vals[1] as u8,
+        config.get_parameter("background_color").vals[2] as u8,
+    );
+
+    let mut frame = Frame::new(size, Color::BLACK);
+    draw_world(
+        &mut frame,
+        world,
+        None,
+        &Viewport::new(world.world_size(), size),
+        layers,
+        background,
+    );
+
+    let path = format!("frame_{:08}.png", world.tick());
+    return match frame.save_png(&path) {
+        Ok(_) => format!("saved {}", path),
+        Err(e) => {
+            log::error!("frame error: {}", &e);
+            "frame error".to_string()
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    #[test]
+    fn cells_pair_rows_of_pixels() {
+        let mut frame = Frame::new((2, 4), Color::BLACK);
+        frame.fill_rects(&[Rect::new(0, 1, 1, 2)], Color::RED);
+
+        let cells = to_cells(&frame);
+
+        assert_eq!(cells.len(), 4);
+        assert_eq!(
+            cells[0],
+            Cell {
+                top: Color::BLACK,
+                bottom: Color::RED
+            }
+        );
+        assert_eq!(
+            cells[1],
+            Cell {
+                top: Color::BLACK,
+                bottom: Color::BLACK
+            }
+        );
+        assert_eq!(
+            cells[2],
+            Cell {
+                top: Color::RED,
+                bottom: Color::BLACK
+            }
+        );
+    }
+
+    #[test]
+    fn frame_shows_the_nest() {
+        let world = fixture::world();
+        let layers = LayerSettings::new(1000.0, 100.0);
+
+        // 40x40 tiles onto 20x20 pixels, the nest sits around pixel 10,10
+        let frame = draw_frame(&world, &layers, Color::RGB(10, 10, 10), (20, 20));
+
+        assert_eq!(frame.size(), (20, 20));
+        assert_eq!(frame.pixel(10, 10), Color::RGB(200, 80, 5));
+        assert_eq!(frame.pixel(1, 18), Color::RGB(10, 10, 10));
+    }
+}
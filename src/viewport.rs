@@ -0,0 +1,131 @@
+use glam::DVec2;
+use sdl2::rect::Rect;
+
+/// Maps world coordinates onto the window. The world is scaled uniformly to fit
+/// and centered, leaving letterbox bars on the sides that don't match its aspect ratio.
+#[derive(Debug, Clone, Copy)]
+pub struct Viewport {
+    world_size: (u32, u32),
+    scale: f64,
+    offset: DVec2,
+}
+
+impl Viewport {
+    pub fn new(world_size: (u32, u32), window_size: (u32, u32)) -> Self {
+        let mut viewport = Self {
+            world_size: world_size,
+            scale: 1.0,
+            offset: DVec2::default(),
+        };
+
+        viewport.resize(window_size);
+
+        return viewport;
+    }
+
+    pub fn resize(&mut self, window_size: (u32, u32)) {
+        let scale_x = window_size.0 as f64 / self.world_size.0.max(1) as f64;
+        let scale_y = window_size.1 as f64 / self.world_size.1.max(1) as f64;
+        self.scale = scale_x.min(scale_y);
+
+        self.offset = DVec2::new(
+            (window_size.0 as f64 - self.world_size.0 as f64 * self.scale) / 2.0,
+            (window_size.1 as f64 - self.world_size.1 as f64 * self.scale) / 2.0,
+        );
+    }
+
+    /// Screen pixels per world unit
+    pub fn scale(&self) -> f64 {
+        return self.scale;
+    }
+
+    pub fn world_to_screen(&self, pos: DVec2) -> DVec2 {
+        return pos * self.scale + self.offset;
+    }
+
+    /// Returns None if the position lies in the letterbox area
+    pub fn screen_to_world(&self, pos: DVec2) -> Option<DVec2> {
+        let world_pos = (pos - self.offset) / self.scale;
+
+        if world_pos.x < 0.0
+            || world_pos.y < 0.0
+            || world_pos.x >= self.world_size.0 as f64
+            || world_pos.y >= self.world_size.1 as f64
+        {
+            return None;
+        }
+
+        return Some(world_pos);
+    }
+
+    /// Maps a rectangle given in world units to the screen. Both corners are
+    /// rounded separately, so neighbouring rectangles never leave gaps.
+    pub fn map_rect(&self, x: f64, y: f64, w: f64, h: f64) -> Rect {
+        let top_left = self.world_to_screen(DVec2::new(x, y));
+        let bottom_right = self.world_to_screen(DVec2::new(x + w, y + h));
+
+        let left = top_left.x.round() as i32;
+        let top = top_left.y.round() as i32;
+
+        return Rect::new(
+            left,
+            top,
+            (bottom_right.x.round() as i32 - left).max(1) as u32,
+            (bottom_right.y.round() as i32 - top).max(1) as u32,
+        );
+    }
+
+    /// Screen area covered by the world
+    pub fn world_rect(&self) -> Rect {
+        return self.map_rect(0.0, 0.0, self.world_size.0 as f64, self.world_size.1 as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screen_and_world_positions_round_trip() {
+        let mut viewport = Viewport::new((400, 300), (800, 600));
+        let points = [
+            DVec2::new(0.0, 0.0),
+            DVec2::new(123.5, 77.25),
+            DVec2::new(399.0, 299.0),
+        ];
+
+        // Exact fit, bars on the sides, then scaled down with bars on top and bottom
+        for window_size in [(800, 600), (1000, 600), (200, 400)] {
+            viewport.resize(window_size);
+            for point in points.iter() {
+                let screen = viewport.world_to_screen(*point);
+                let back = viewport.screen_to_world(screen).unwrap();
+                assert!((back - *point).length() < 1e-9, "{:?} {:?}", point, back);
+            }
+        }
+    }
+
+    #[test]
+    fn a_different_aspect_ratio_gets_centered_bars() {
+        // Window too wide, bars left and right
+        let mut viewport = Viewport::new((400, 300), (1000, 600));
+        assert_eq!(viewport.scale(), 2.0);
+        assert_eq!(viewport.world_rect(), Rect::new(100, 0, 800, 600));
+        assert_eq!(viewport.screen_to_world(DVec2::new(50.0, 300.0)), None);
+        assert_eq!(viewport.screen_to_world(DVec2::new(950.0, 300.0)), None);
+        assert_eq!(
+            viewport.screen_to_world(DVec2::new(100.0, 0.0)),
+            Some(DVec2::new(0.0, 0.0))
+        );
+
+        // Window too tall, bars on top and bottom
+        viewport.resize((400, 600));
+        assert_eq!(viewport.scale(), 1.0);
+        assert_eq!(viewport.world_rect(), Rect::new(0, 150, 400, 300));
+        assert_eq!(viewport.screen_to_world(DVec2::new(200.0, 100.0)), None);
+        assert_eq!(
+            viewport.world_to_screen(DVec2::new(200.0, 150.0)),
+            DVec2::new(200.0, 300.0)
+        );
+    }
+}
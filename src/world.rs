@@ -1,140 +1,968 @@
-use rand::{distributions::Uniform, prelude::Distribution};
+use glam::DVec2;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
-use sdl2::{pixels::Color, rect::Rect, render::Canvas, video::Window};
+use sdl2::pixels::Color;
 
-use crate::{colony::Colony, food::Food, marker::Marker, tile::Tile};
+use crate::{
+    ant::{Ant, NUM_STATES},
+    ant_hill::{AntHill, NestConfig},
+    colony::{Colony, Lifecycle},
+    command::Command,
+    config::Config,
+    food::{FoodEvent, FoodSource, FoodSources},
+    food_gen::{FoodGenerator, FoodPile},
+    grid::{DirtyRegion, Grid},
+    marker::Marker,
+    stats::WorldStats,
+    tile::{Terrain, Tile},
+    traits::{is_trait_parameter, AntTraits},
+    util::map_pos_to_grid,
+};
+
+/// Numbers `apply_live_parameters` picks up while the world runs, together with
+/// the trait distributions and the nest color. Everything else is only read
+/// when a world is created.
+pub const LIVE_PARAMETERS: [&str; 7] = [
+    "pheromone_decay",
+    "ant_energy",
+    "ant_energy_drain",
+    "energy_per_food",
+    "ant_birth_cost",
+    "max_ants",
+    "check_invariants",
+];
 
 pub struct World {
     colony: Colony,
-    grid: Vec<Vec<Tile>>,
-    window_size: (u32, u32),
-    grid_size: (u32, u32),
-    tile_size: f64,
+    grid: Grid,
+    world_size: (u32, u32),
+    tick: u64,
+
+    food_sources: FoodSources,
+
+    seed: u64,
+    random_gen: StdRng,
+
+    // Marker strength lost per tick, 0 keeps trails forever
+    pheromone_decay: f64,
 
-    inserted_food_coords: Vec<(u32, u32)>,
+    // Runs check_invariants after every tick and panics on the first violation
+    debug_checks: bool,
 }
 
 impl World {
+    /// World as described by the config, shared by the window and headless runs
+    pub fn from_config(config: &Config) -> Self {
+        let mut world = World::new(
+            config.get_parameter("num_ants").vals[0] as u32,
+            (
+                config.get_parameter("grid_dim").vals[0] as u32,
+                config.get_parameter("grid_dim").vals[1] as u32,
+            ),
+            config.get_parameter("tile_size").vals[0],
+            Color::BLACK,
+            &FoodGenerator::from_config(config),
+            AntTraits::from_config(config),
+            Lifecycle::from_config(config),
+            &NestConfig::from_config(config),
+            World::seed_from_config(config),
+        );
+        world.pheromone_decay = config.get_parameter("pheromone_decay").vals[0];
+        world.debug_checks = config.get_parameter("check_invariants").vals[0] != 0.0;
+
+        return world;
+    }
+
+    /// "seed random" picks a new seed every run, the chosen one is logged so it can be reused
+    pub fn seed_from_config(config: &Config) -> u64 {
+        // Parsed from the text, seeds above 2^53 don't survive a round trip through f64
+        let seed = match config.get_parameter("seed").text.parse::<u64>() {
+            Ok(seed) => seed,
+            Err(_) => rand::thread_rng().gen(),
+        };
+        log::info!("world seed {}", seed);
+
+        return seed;
+    }
+
     pub fn new(
         colony_size: u32,
         grid_size: (u32, u32),
-        window_size: &mut (u32, u32),
         desired_tile_size: f64,
         ant_color: Color,
 
-        min_max_food: (u32, u32),
+        food_generator: &FoodGenerator,
+        ant_traits: AntTraits,
+        life: Lifecycle,
+        nest: &NestConfig,
+        seed: u64,
     ) -> Self {
-        let window_x = grid_size.0 as f64 * desired_tile_size;
-        let window_y = grid_size.1 as f64 * desired_tile_size;
+        // World units are independent of the window, the viewport scales them for rendering
+        let world_x = grid_size.0 as f64 * desired_tile_size;
+        let world_y = grid_size.1 as f64 * desired_tile_size;
 
-        *window_size = (window_x.ceil() as u32, window_y.ceil() as u32).clone();
+        let world_size = (world_x.ceil() as u32, world_y.ceil() as u32);
 
-        // Grid init
-        let mut tiles: Vec<Vec<Tile>> = Vec::new();
-        let empty_marker = Marker::new(0);
-        let empty_food = Food { concentration: 0 };
+        // Everything random in the world comes from this, so a seed reproduces a run
+        let mut random_gen = StdRng::seed_from_u64(seed);
 
-        // Random food setup
-        let mut random_gen = rand::thread_rng();
+        let nest_grid_pos = nest.pos.unwrap_or((grid_size.0 / 2, grid_size.1 / 2));
+        let nest_pos = DVec2::new(
+            (nest_grid_pos.0 as f64 + 0.5) * desired_tile_size,
+            (nest_grid_pos.1 as f64 + 0.5) * desired_tile_size,
+        );
 
-        let random_food_range_amount = Uniform::from(1500..3000);
-        let random_food_range_num = Uniform::from(min_max_food.0..min_max_food.1);
-        let random_food_range_x = Uniform::from(0..grid_size.0);
-        let random_food_range_y = Uniform::from(0..grid_size.0);
+        let mut world = Self {
+            colony: Colony::new(
+                colony_size,
+                ant_color,
+                AntHill::new(nest_pos, nest, life.energy_per_food),
+                ant_traits,
+                life,
+                &mut random_gen,
+            ),
+            grid: Grid::new(grid_size),
+            world_size: world_size,
+            tick: 0,
+            food_sources: FoodSources::new(),
 
-        // Grid init
-        for x in 0..grid_size.0 {
-            tiles.push(Vec::new());
-            for _ in 0..grid_size.1 {
-                tiles[x as usize].push(Tile {
-                    markers: (empty_marker, empty_marker),
-                    food: empty_food,
-                });
-            }
-        }
+            seed: seed,
+            random_gen: random_gen,
 
-        let mut world = Self {
-            colony: Colony::new(colony_size, ant_color, (0, 0), *window_size),
-            grid: tiles,
-            window_size: *window_size,
-            tile_size: desired_tile_size,
-            grid_size: grid_size,
-            inserted_food_coords: Vec::new(),
+            pheromone_decay: 0.0,
+            debug_checks: false,
         };
 
         // Generate food
-        for _ in 0..random_food_range_num.sample(&mut random_gen) {
-            world.insert_food(
-                (
-                    random_food_range_x.sample(&mut random_gen),
-                    random_food_range_y.sample(&mut random_gen),
-                ),
-                random_food_range_amount.sample(&mut random_gen),
-            );
+        let nest = world.colony.ant_hill.map_pos_to_grid(grid_size, world_size);
+        for pile in food_generator.generate(&mut world.random_gen, grid_size, nest) {
+            world.add_food_pile(&pile, food_generator.regrowth_rate);
         }
 
         return world;
     }
 
+    /// Whether `apply_live_parameters` takes over a config key
+    pub fn is_live_parameter(name: &str) -> bool {
+        return LIVE_PARAMETERS.contains(&name) || name == "nest_color" || is_trait_parameter(name);
+    }
+
+    /// Takes over the live parameters from the config. Ants already alive keep their energy
+    /// and redraw traits whose distribution changed. Nothing changes if a value is invalid.
+    pub fn apply_live_parameters(&mut self, config: &Config) -> Result<(), String> {
+        for name in LIVE_PARAMETERS.iter() {
+            config.get_values(name, 1)?;
+        }
+        if config.get_parameter("pheromone_decay").vals[0] < 0.0 {
+            return Err("pheromone_decay can't be negative".to_string());
+        }
+        let nest_color = config.get_values("nest_color", 3)?;
+        // The caste list and shares are structural, only the distributions are taken over
+        let traits = self.colony.traits.reparse_profiles(config)?;
+
+        self.pheromone_decay = config.get_parameter("pheromone_decay").vals[0];
+        self.debug_checks = config.get_parameter("check_invariants").vals[0] != 0.0;
+
+        self.colony.life = Lifecycle::from_config(config);
+        self.colony
+            .ant_hill
+            .set_energy_per_food(self.colony.life.energy_per_food);
+        self.colony.ant_hill.set_color(Color::RGB(
+            nest_color[0] as u8,
+            nest_color[1] as u8,
+            nest_color[2] as u8,
+        ));
+        self.colony.set_traits(traits, &mut self.random_gen);
+
+        return Ok(());
+    }
+
     pub fn update(&mut self) {
+        self.food_sources.set_tick(self.tick);
+
         self.colony.update(
-            self.window_size,
-            self.grid_size,
+            self.world_size,
+            self.grid.size(),
             &mut self.grid,
-            &mut self.inserted_food_coords,
+            &mut self.food_sources,
+            &mut self.random_gen,
         );
+
+        self.food_sources.regrow(&mut self.grid);
         //self.update_tiles();
-    }
+        if self.pheromone_decay > 0.0 {
+            self.fade_markers();
+        }
 
-    pub fn render(&self, canvas: &mut Canvas<Window>) {
-        self.render_tiles(canvas);
-        self.colony.render(canvas);
+        self.tick += 1;
+
+        if self.debug_checks {
+            if let Err(e) = self.check_invariants() {
+                panic!("invariant violated at tick {}: {}", self.tick, e);
+            }
+        }
     }
 
-    fn render_tiles(&self, canvas: &mut Canvas<Window>) {
-        let previous_color = canvas.draw_color();
+    /// Consistency checks for debugging, too slow to run every tick in normal use
+    pub fn check_invariants(&self) -> Result<(), String> {
+        for ant in self.colony.ants.iter() {
+            let pos = ant.pos();
+            if !(pos.x >= 0.0
+                && pos.y >= 0.0
+                && pos.x <= self.world_size.0 as f64
+                && pos.y <= self.world_size.1 as f64)
+            {
+                return Err(format!("ant {} left the world at {}", ant.id(), pos));
+            }
 
-        for x in 0..self.grid.len() {
-            for y in 0..self.grid[x].len() {
-                canvas.set_draw_color(self.grid[x][y].get_color());
+            if ant.state() as usize >= NUM_STATES {
+                return Err(format!(
+                    "ant {} has invalid state {}",
+                    ant.id(),
+                    ant.state()
+                ));
+            }
+        }
 
-                match canvas.fill_rect(Rect::new(
-                    x as i32 * self.tile_size as i32,
-                    y as i32 * self.tile_size as i32,
-                    self.tile_size as u32,
-                    self.tile_size as u32,
-                )) {
-                    Ok(_) => {}
-                    Err(e) => {
-                        log::error!("render error: {}", &e);
+        let mut food_on_tiles: u64 = 0;
+        for (x, column) in self.grid.tiles().iter().enumerate() {
+            for (y, tile) in column.iter().enumerate() {
+                for marker in [tile.markers.0, tile.markers.1].iter() {
+                    if !marker.strength.is_finite() || marker.strength < 0.0 {
+                        return Err(format!(
+                            "marker at {:?} has strength {}",
+                            (x, y),
+                            marker.strength
+                        ));
                     }
                 }
+
+                if tile.terrain == Terrain::Wall
+                    && (tile.food.concentration != 0 || tile.food.source.is_some())
+                {
+                    return Err(format!("wall at {:?} holds food", (x, y)));
+                }
+
+                food_on_tiles += tile.food.concentration as u64;
             }
         }
 
-        canvas.set_draw_color(previous_color);
+        self.food_sources.check_invariants(&self.grid)?;
+
+        // Harvested food is either carried, delivered or lost with an ant starved on a wall
+        let food_carried: u64 = self
+            .colony
+            .ants
+            .iter()
+            .map(|ant| ant.carrying() as u64)
+            .sum();
+        let accounted = food_on_tiles
+            + food_carried
+            + self.colony.ant_hill.food_collected
+            + self.colony.food_lost;
+        let expected = self.food_sources.total_added() - self.food_sources.total_removed();
+
+        if accounted != expected {
+            return Err(format!(
+                "food not conserved: {} on tiles, {} carried, {} delivered, {} lost, expected {}",
+                food_on_tiles,
+                food_carried,
+                self.colony.ant_hill.food_collected,
+                self.colony.food_lost,
+                expected
+            ));
+        }
+
+        return Ok(());
+    }
+
+    // * Read-only views for the front-ends
+
+    pub fn grid(&self) -> &Grid {
+        return &self.grid;
+    }
+
+    pub fn ants(&self) -> &[Ant] {
+        return &self.colony.ants;
+    }
+
+    pub fn ant_hill(&self) -> &AntHill {
+        return &self.colony.ant_hill;
+    }
+
+    pub fn ant_color(&self) -> Color {
+        return self.colony.ant_color();
+    }
+
+    /// Tiles changed since the last call, for renderers that only redraw those
+    pub fn take_dirty_tiles(&mut self) -> Option<DirtyRegion> {
+        return self.grid.take_dirty();
+    }
+
+    /// Walks the whole grid, so only call this when the numbers are actually shown
+    pub fn stats(&self) -> WorldStats {
+        let mut stats = WorldStats {
+            tick: self.tick,
+            num_ants: self.colony.ants.len(),
+            food_collected: self.colony.ant_hill.food_collected,
+            food_stored: self.colony.ant_hill.food_stored,
+            births: self.colony.births,
+            deaths: self.colony.deaths,
+            trips_completed: self.colony.ant_hill.trips_completed,
+            trip_ticks_total: self.colony.ant_hill.trip_ticks_total,
+            ..WorldStats::default()
+        };
+
+        for ant in self.colony.ants.iter() {
+            if (ant.state() as usize) < NUM_STATES {
+                stats.ants_per_state[ant.state() as usize] += 1;
+            }
+            stats.food_carried += ant.carrying() as u64;
+        }
+
+        // Sources without tiles were removed, they are neither active nor depleted
+        for source in self
+            .food_sources
+            .iter()
+            .filter(|source| !source.tiles().is_empty())
+        {
+            if source.is_depleted() {
+                stats.food_sources_depleted += 1;
+            } else {
+                stats.food_sources_active += 1;
+            }
+        }
+
+        for column in self.grid.tiles().iter() {
+            for tile in column.iter() {
+                stats.food_remaining += tile.food.concentration as u64;
+                stats.to_home_mass += tile.to_home_strength();
+                stats.to_food_mass += tile.to_food_strength();
+            }
+        }
+
+        return stats;
+    }
+
+    /// Forces every tile to be redrawn, e.g. after the render layer changed
+    pub fn invalidate_tiles(&mut self) {
+        self.grid.mark_all_dirty();
+    }
+
+    pub fn seed(&self) -> u64 {
+        return self.seed;
+    }
+
+    pub fn food_collected(&self) -> u64 {
+        return self.colony.ant_hill.food_collected;
+    }
+
+    pub fn tick(&self) -> u64 {
+        return self.tick;
+    }
+
+    pub fn world_size(&self) -> (u32, u32) {
+        return self.world_size;
+    }
+
+    pub fn grid_size(&self) -> (u32, u32) {
+        return self.grid.size();
+    }
+
+    pub fn world_to_grid(&self, pos: DVec2) -> (u32, u32) {
+        return map_pos_to_grid(pos, self.grid.size(), self.world_size);
+    }
+
+    pub fn tile(&self, grid_pos: (u32, u32)) -> &Tile {
+        return self.grid.tile(grid_pos);
+    }
+
+    pub fn caste_name(&self, caste: usize) -> &str {
+        return self.colony.traits.caste_name(caste);
+    }
+
+    pub fn ant(&self, id: u64) -> Option<&Ant> {
+        return self.colony.ants.iter().find(|ant| ant.id() == id);
+    }
+
+    /// Closest ant within max_distance world units of pos
+    pub fn ant_near(&self, pos: DVec2, max_distance: f64) -> Option<&Ant> {
+        let mut closest: Option<&Ant> = None;
+        let mut closest_distance = max_distance;
+
+        for ant in self.colony.ants.iter() {
+            let distance = ant.pos().distance(pos);
+            if distance <= closest_distance {
+                closest_distance = distance;
+                closest = Some(ant);
+            }
+        }
+
+        return closest;
+    }
+
+    fn fade_markers(&mut self) {
+        let amount = self.pheromone_decay;
+        self.grid.tiles_mut().par_iter_mut().for_each(|column| {
+            column.iter_mut().for_each(|tile| {
+                tile.fade_markers(amount);
+            })
+        })
     }
 
     // ! Working, but doesn't really add anything
     #[allow(dead_code)]
     fn update_tiles(&mut self) {
-        self.grid.par_iter_mut().for_each(|column| {
+        self.grid.tiles_mut().par_iter_mut().for_each(|column| {
             column.par_iter_mut().for_each(|tile| {
                 tile.update();
             })
         })
     }
 
-    pub fn insert_food(&mut self, grid_pos: (u32, u32), amount: u32) {
+    pub fn grid_to_world(&self, grid_pos: (u32, u32)) -> DVec2 {
+        let grid_size = self.grid.size();
+
+        return DVec2::new(
+            (grid_pos.0 as f64 + 0.5) * self.world_size.0 as f64 / grid_size.0 as f64,
+            (grid_pos.1 as f64 + 0.5) * self.world_size.1 as f64 / grid_size.1 as f64,
+        );
+    }
+
+    /// Applies an edit, commands with a position outside the grid are refused
+    pub fn apply(&mut self, command: &Command) -> Result<(), String> {
+        let (width, height) = self.grid.size();
+        if let Some(pos) = command
+            .positions()
+            .into_iter()
+            .find(|pos| pos.0 >= width || pos.1 >= height)
+        {
+            return Err(format!(
+                "{},{} in '{}' is outside the {}x{} grid",
+                pos.0,
+                pos.1,
+                command.to_line(),
+                width,
+                height
+            ));
+        }
+
+        match *command {
+            Command::PlaceFood {
+                pos,
+                radius,
+                amount,
+            } => {
+                // The amount is per tile, the source holds the total
+                let num_tiles = self.grid.tiles_in_radius(pos, radius).len() as u64;
+                self.add_food_source(pos, radius, amount as u64 * num_tiles, 0.0);
+            }
+            Command::PlaceWall { pos, radius } => {
+                for tile_pos in self.grid.tiles_in_radius(pos, radius) {
+                    self.place_wall(tile_pos);
+                }
+            }
+            Command::Erase { pos, radius } => {
+                for tile_pos in self.grid.tiles_in_radius(pos, radius) {
+                    self.remove_food(tile_pos);
+                    self.grid.tile_mut(tile_pos).terrain = Terrain::Ground;
+                }
+            }
+            Command::PaintMarker {
+                pos,
+                radius,
+                m_type,
+                strength,
+            } => {
+                for tile_pos in self.grid.tiles_in_radius(pos, radius) {
+                    let tile = self.grid.tile_mut(tile_pos);
+                    if tile.terrain == Terrain::Wall {
+                        continue;
+                    }
+
+                    let marker = if m_type == 1 {
+                        &mut tile.markers.0
+                    } else {
+                        &mut tile.markers.1
+                    };
+                    marker.m_type = m_type;
+                    marker.strength = marker.strength.max(strength);
+                }
+            }
+            Command::EraseMarkers { pos, radius } => {
+                let empty_marker = Marker::new(0);
+                for tile_pos in self.grid.tiles_in_radius(pos, radius) {
+                    self.grid.tile_mut(tile_pos).markers = (empty_marker, empty_marker);
+                }
+            }
+            Command::MoveHill { pos } => {
+                self.colony.ant_hill.pos = self.grid_to_world(pos);
+            }
+            Command::PlaceWallLine { from, to } => self.place_wall_line(from, to),
+            Command::RemoveNearestFood { pos } => {
+                self.remove_nearest_food(pos);
+            }
+        }
+
+        return Ok(());
+    }
+
+    /// Walls over the food and trails of a tile
+    fn place_wall(&mut self, grid_pos: (u32, u32)) {
+        self.remove_food(grid_pos);
+
         let empty_marker = Marker::new(0);
+        let tile = self.grid.tile_mut(grid_pos);
+        tile.terrain = Terrain::Wall;
+        tile.markers = (empty_marker, empty_marker);
+    }
+
+    /// One tile per step along the longer axis, ends outside the grid are cut off
+    pub fn place_wall_line(&mut self, from: (u32, u32), to: (u32, u32)) {
+        let (dx, dy) = (to.0 as f64 - from.0 as f64, to.1 as f64 - from.1 as f64);
+        let steps = dx.abs().max(dy.abs()).max(1.0) as u32;
+        let grid_size = self.grid.size();
+
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            let x = (from.0 as f64 + dx * t).round() as u32;
+            let y = (from.1 as f64 + dy * t).round() as u32;
+            if x < grid_size.0 && y < grid_size.1 {
+                self.place_wall((x, y));
+            }
+        }
+    }
+
+    /// Removes the source closest to grid_pos, returns its id. Depleted sources count,
+    /// they would regrow otherwise.
+    pub fn remove_nearest_food(&mut self, grid_pos: (u32, u32)) -> Option<usize> {
+        let distance = |pos: (u32, u32)| -> i64 {
+            let dx = pos.0 as i64 - grid_pos.0 as i64;
+            let dy = pos.1 as i64 - grid_pos.1 as i64;
+            return dx * dx + dy * dy;
+        };
+
+        let id = self
+            .food_sources
+            .iter()
+            .filter(|source| !source.tiles().is_empty())
+            .min_by_key(|source| distance(source.pos))
+            .map(|source| source.id)?;
+
+        self.food_sources.remove_source(&mut self.grid, id);
+
+        return Some(id);
+    }
+
+    pub fn food_source(&self, id: usize) -> Option<&FoodSource> {
+        return self.food_sources.get(id);
+    }
+
+    /// Depletion and regrowth events since the last call
+    pub fn drain_food_events(&mut self) -> Vec<FoodEvent> {
+        return self.food_sources.drain_events();
+    }
+
+    /// Creates a food source spreading amount over the ground tiles within radius
+    pub fn add_food_source(
+        &mut self,
+        grid_pos: (u32, u32),
+        radius: u32,
+        amount: u64,
+        regrowth_rate: f64,
+    ) -> Option<usize> {
+        let tiles = self.grid.tiles_in_radius(grid_pos, radius);
+        return self.add_food_tiles(grid_pos, radius, tiles, amount, regrowth_rate);
+    }
+
+    pub fn add_food_pile(&mut self, pile: &FoodPile, regrowth_rate: f64) -> Option<usize> {
+        let tiles = match &pile.tiles {
+            Some(tiles) => tiles.clone(),
+            None => self.grid.tiles_in_radius(pile.pos, pile.radius),
+        };
+        return self.add_food_tiles(pile.pos, pile.radius, tiles, pile.amount, regrowth_rate);
+    }
+
+    // Walls never hold food, so they are left out of the source
+    fn add_food_tiles(
+        &mut self,
+        grid_pos: (u32, u32),
+        radius: u32,
+        tiles: Vec<(u32, u32)>,
+        amount: u64,
+        regrowth_rate: f64,
+    ) -> Option<usize> {
+        let tiles: Vec<(u32, u32)> = tiles
+            .into_iter()
+            .filter(|tile_pos| self.grid.tile(*tile_pos).terrain == Terrain::Ground)
+            .collect();
+
+        return self.food_sources.add(
+            &mut self.grid,
+            grid_pos,
+            radius,
+            tiles,
+            amount,
+            regrowth_rate,
+        );
+    }
+
+    pub fn remove_food(&mut self, grid_pos: (u32, u32)) {
+        self.food_sources.remove_tile(&mut self.grid, grid_pos);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ant::state_name, fixture, traits::TraitDist};
+    use proptest::prelude::*;
+
+    #[test]
+    fn fixture_runs_are_identical() {
+        // More ants and food than the plain fixture, so there is something to diverge
+        let mut config = fixture::config();
+        config.set_parameter("num_ants", "30");
+        config.set_parameter("max_ants", "30");
+        config.set_parameter("food_pile_count", "3,3");
+        config.set_parameter("food_pile_amount", "100,300");
+        config.set_parameter("food_pile_radius", "2");
+
+        let mut a = World::from_config(&config);
+        let mut b = World::from_config(&config);
+
+        for _ in 0..200 {
+            a.update();
+            b.update();
+        }
+
+        assert_eq!(a.stats(), b.stats());
+        for (ant_a, ant_b) in a.colony.ants.iter().zip(b.colony.ants.iter()) {
+            assert_eq!(ant_a.pos(), ant_b.pos());
+            assert_eq!(ant_a.state(), ant_b.state());
+            assert_eq!(ant_a.carrying(), ant_b.carrying());
+        }
+        for (column_a, column_b) in a.grid.tiles().iter().zip(b.grid.tiles().iter()) {
+            for (tile_a, tile_b) in column_a.iter().zip(column_b.iter()) {
+                assert_eq!(tile_a.food.concentration, tile_b.food.concentration);
+                assert_eq!(tile_a.to_home_strength(), tile_b.to_home_strength());
+                assert_eq!(tile_a.to_food_strength(), tile_b.to_food_strength());
+            }
+        }
+    }
+
+    #[test]
+    fn live_parameters_reach_the_running_world() {
+        let mut world = fixture::world();
+        let mut config = fixture::config();
+        let size = world.colony.ants[0].size();
+
+        config.set_parameter("ant_speed", "fixed,5");
+        config.set_parameter("pheromone_decay", "10");
+        world.apply_live_parameters(&config).unwrap();
+
+        // Only the changed distribution is drawn again
+        assert_eq!(world.colony.ants[0].speed(), 5.0);
+        assert_eq!(world.colony.ants[0].size(), size);
+
+        world
+            .apply(&Command::PaintMarker {
+                pos: (5, 5),
+                radius: 0,
+                m_type: 1,
+                strength: 150.0,
+            })
+            .unwrap();
+        world.update();
+        assert_eq!(world.tile((5, 5)).to_home_strength(), 140.0);
+
+        // A broken value leaves everything as it was
+        config.set_parameter("ant_speed", "fixed,6");
+        config.set_parameter("nest_color", "1,2");
+        assert!(world.apply_live_parameters(&config).is_err());
+        assert_eq!(world.colony.ants[0].speed(), 5.0);
+    }
+
+    #[test]
+    fn live_parameters_keep_the_castes_and_refuse_negative_decay() {
+        let mut world = fixture::world();
+        let mut config = fixture::config();
+        let castes = world.colony.traits.clone();
+
+        // A pending caste change stays pending, the trait edit goes through
+        config.set_parameter("ant_castes", "soldier,worker");
+        config.set_parameter("caste_soldier_share", "1");
+        config.set_parameter("caste_worker_share", "5");
+        config.set_parameter("ant_speed", "fixed,5");
+        world.apply_live_parameters(&config).unwrap();
+
+        assert_eq!(world.colony.traits.castes.len(), castes.castes.len());
+        for (now, before) in world.colony.traits.castes.iter().zip(castes.castes.iter()) {
+            assert_eq!(now.name, before.name);
+            assert_eq!(now.share, before.share);
+            assert_eq!(now.traits.speed, TraitDist::Fixed(5.0));
+        }
+
+        config.set_parameter("pheromone_decay", "-1");
+        assert!(world.apply_live_parameters(&config).is_err());
+        assert!(world.pheromone_decay >= 0.0);
+    }
+
+    #[test]
+    fn commands_outside_the_grid_are_refused() {
+        let mut world = fixture::world();
+        let hill_pos = world.ant_hill().pos;
+
+        assert!(world.apply(&Command::MoveHill { pos: (40, 5) }).is_err());
+        assert!(world
+            .apply(&Command::PlaceFood {
+                pos: (5, 400),
+                radius: 1,
+                amount: 10,
+            })
+            .is_err());
+        assert!(world
+            .apply(&Command::PlaceWallLine {
+                from: (0, 0),
+                to: (39, 40),
+            })
+            .is_err());
+        assert_eq!(world.ant_hill().pos, hill_pos);
+        assert_eq!(world.stats().food_remaining, 0);
+        assert_eq!(world.tile((0, 0)).terrain, Terrain::Ground);
 
-        self.grid[grid_pos.0 as usize][grid_pos.1 as usize] = Tile {
-            markers: (empty_marker, empty_marker),
-            food: Food {
-                concentration: amount,
+        // Negative numbers used to end up as 0
+        assert!(Command::parse(&["move_hill", "-3", "4"]).is_err());
+
+        world.apply(&Command::MoveHill { pos: (39, 39) }).unwrap();
+        assert_eq!(world.ant_hill().pos, DVec2::new(395.0, 395.0));
+    }
+
+    #[test]
+    fn ants_born_at_a_hill_on_the_border_stay_inside() {
+        let mut config = fixture::config();
+        config.set_parameter("ant_birth_cost", "1");
+        config.set_parameter("max_ants", "20");
+        let mut world = World::from_config(&config);
+
+        world.apply(&Command::MoveHill { pos: (0, 0) }).unwrap();
+        world.colony.ant_hill.food_stored = 10;
+        world.update();
+
+        assert_eq!(world.colony.ants.len(), 11);
+        let (width, height) = world.world_size();
+        for ant in world.colony.ants.iter() {
+            assert!(ant.pos().x >= 0.0 && ant.pos().x <= width as f64);
+            assert!(ant.pos().y >= 0.0 && ant.pos().y <= height as f64);
+        }
+    }
+
+    #[test]
+    fn stored_food_pays_for_births_up_to_max_ants() {
+        let mut config = fixture::config();
+        config.set_parameter("ant_birth_cost", "5");
+        config.set_parameter("max_ants", "3");
+        let mut world = World::from_config(&config);
+
+        world.colony.ant_hill.food_stored = 12;
+        world.update();
+
+        assert_eq!(world.colony.ants.len(), 3);
+        assert_eq!(world.colony.births, 2);
+        assert_eq!(world.colony.ant_hill.food_stored, 2);
+    }
+
+    #[test]
+    fn starved_ants_drop_their_food_where_they_die() {
+        let mut world = fixture::world();
+
+        let ant_tile = world.world_to_grid(world.colony.ants[0].pos());
+        world.add_food_source((ant_tile.0 + 2, ant_tile.1), 0, 5, 0.0);
+        while world.colony.ants[0].carrying() == 0 {
+            world.update();
+        }
+
+        let ant_tile = world.world_to_grid(world.colony.ants[0].pos());
+        let food_before = world.tile(ant_tile).food.concentration;
+        world.colony.life.energy_drain = f64::MAX;
+        world.update();
+
+        assert!(world.colony.ants.is_empty());
+        assert_eq!(world.colony.deaths, 1);
+        assert_eq!(world.colony.food_lost, 0);
+        assert_eq!(world.tile(ant_tile).food.concentration, food_before + 1);
+        assert_eq!(world.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn seeds_above_f64_precision_are_kept() {
+        let mut config = fixture::config();
+
+        config.set_parameter("seed", "18446744073709551557");
+        assert_eq!(World::seed_from_config(&config), 18446744073709551557);
+
+        config.set_parameter("seed", "83482131206128007");
+        assert_eq!(World::seed_from_config(&config), 83482131206128007);
+    }
+
+    #[test]
+    fn ant_next_to_food_targets_it_and_takes_from_the_tile() {
+        let mut world = fixture::world();
+
+        let ant_tile = world.world_to_grid(world.colony.ants[0].pos());
+        let food_tile = (ant_tile.0 + 2, ant_tile.1);
+        world.add_food_source(food_tile, 0, 5, 0.0);
+
+        world.update();
+        let ant = &world.colony.ants[0];
+        assert_eq!(state_name(ant.state()), "target food");
+        assert_eq!(ant.current_target_tile(), food_tile);
+
+        for _ in 0..20 {
+            world.update();
+            if world.colony.ants[0].carrying() > 0 {
+                break;
+            }
+        }
+
+        assert_eq!(world.colony.ants[0].carrying(), 1);
+        assert_eq!(world.tile(food_tile).food.concentration, 4);
+        assert_eq!(world.food_source(0).unwrap().amount, 4);
+    }
+
+    #[test]
+    fn emptied_tiles_under_walls_and_erased_ground_stay_empty() {
+        let mut world = fixture::world();
+        let id = world.add_food_source((5, 5), 1, 50, 5.0).unwrap();
+
+        // Both tiles harvested down to nothing while the rest of the source still regrows
+        for pos in [(5, 5), (6, 5)] {
+            while world.food_sources.harvest(&mut world.grid, pos) {}
+        }
+        world
+            .apply(&Command::PlaceWall {
+                pos: (5, 5),
+                radius: 0,
+            })
+            .unwrap();
+        world
+            .apply(&Command::Erase {
+                pos: (6, 5),
+                radius: 0,
+            })
+            .unwrap();
+
+        for _ in 0..50 {
+            world.food_sources.regrow(&mut world.grid);
+        }
+
+        for pos in [(5, 5), (6, 5)] {
+            assert_eq!(world.tile(pos).food.concentration, 0);
+            assert_eq!(world.tile(pos).food.source, None);
+            assert!(!world.food_source(id).unwrap().tiles().contains(&pos));
+        }
+        assert!(world.food_source(id).unwrap().amount > 0);
+    }
+
+    #[test]
+    fn removed_food_sources_stay_gone() {
+        let mut world = fixture::world();
+        let depleted = world.add_food_source((5, 5), 0, 3, 1.0).unwrap();
+        let regrowing = world.add_food_source((30, 30), 1, 40, 2.0).unwrap();
+        while world.food_sources.harvest(&mut world.grid, (5, 5)) {}
+
+        // A depleted source is still the nearest one
+        assert_eq!(world.remove_nearest_food((6, 6)), Some(depleted));
+        assert_eq!(world.remove_nearest_food((6, 6)), Some(regrowing));
+        assert_eq!(world.remove_nearest_food((6, 6)), None);
+
+        for _ in 0..50 {
+            world.food_sources.regrow(&mut world.grid);
+        }
+
+        let stats = world.stats();
+        assert_eq!(stats.food_remaining, 0);
+        assert_eq!(stats.food_sources_active + stats.food_sources_depleted, 0);
+        assert_eq!(world.food_sources.check_invariants(&world.grid), Ok(()));
+    }
+
+    fn edit(kind: u8, pos: (u32, u32), radius: u32) -> Command {
+        return match kind {
+            0 => Command::PlaceFood {
+                pos: pos,
+                radius: radius,
+                amount: 50,
+            },
+            1 => Command::PlaceWall {
+                pos: pos,
+                radius: radius,
+            },
+            2 => Command::Erase {
+                pos: pos,
+                radius: radius,
+            },
+            3 => Command::PaintMarker {
+                pos: pos,
+                radius: radius,
+                m_type: 2,
+                strength: 500.0,
             },
+            4 => Command::EraseMarkers {
+                pos: pos,
+                radius: radius,
+            },
+            _ => Command::MoveHill { pos: pos },
         };
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(24))]
 
-        self.inserted_food_coords.push(grid_pos);
+        #[test]
+        fn invariants_hold_for_random_worlds(
+            seed in any::<u64>(),
+            num_ants in 1u32..40,
+            grid in 10u32..40,
+            layout in 0usize..3,
+            piles in 0u32..6,
+            regrowth in 0.0f64..2.0,
+            entrances in 0u32..4,
+            drain in 0.0f64..20.0,
+            birth_cost in 0u32..30,
+            edits in proptest::collection::vec(
+                (0u64..150, 0u8..6, any::<(u32, u32)>(), 0u32..3),
+                0..10
+            ),
+        ) {
+            let mut config = fixture::config();
+            config.set_parameter("check_invariants", "0");
+            config.set_parameter("seed", &seed.to_string());
+            config.set_parameter("num_ants", &num_ants.to_string());
+            config.set_parameter("max_ants", "60");
+            config.set_parameter("grid_dim", &format!("{},{}", grid, grid));
+            config.set_parameter("nest_pos", "center");
+            config.set_parameter("nest_entrances", &entrances.to_string());
+            config.set_parameter("food_layout", ["uniform", "clusters", "ring"][layout]);
+            config.set_parameter("food_pile_count", &format!("0,{}", piles));
+            config.set_parameter("food_pile_amount", "10,400");
+            config.set_parameter("food_ring_distance", &(grid / 3).to_string());
+            config.set_parameter("food_regrowth_rate", &regrowth.to_string());
+            config.set_parameter("ant_energy_drain", &drain.to_string());
+            config.set_parameter("ant_birth_cost", &birth_cost.to_string());
+
+            let mut world = World::from_config(&config);
+            prop_assert_eq!(world.check_invariants(), Ok(()));
+
+            for tick in 0..150 {
+                for (edit_tick, kind, pos, radius) in edits.iter() {
+                    if *edit_tick == tick {
+                        world.apply(&edit(*kind, (pos.0 % grid, pos.1 % grid), *radius)).unwrap();
+                    }
+                }
+
+                world.update();
+                prop_assert_eq!(world.check_invariants(), Ok(()), "tick {}", tick);
+            }
+        }
     }
 }